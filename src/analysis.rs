@@ -0,0 +1,679 @@
+use super::core::{Pack, Cell, DictEntry, Location};
+use hashbrown::{HashMap, HashSet};
+use alloc::{vec::Vec, string::String, format};
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Kind of cell that a parsing word takes from the concat.
+pub enum Parsed {
+    Word,
+    String,
+    Integer,
+    Any,
+}
+
+impl Parsed {
+    fn accepts(self, cell: Option<&Cell>) -> bool {
+        matches!(
+            (self, cell),
+            (Parsed::Word, Some(Cell::Word(_))) | (Parsed::String, Some(Cell::String(_))) |
+            (Parsed::Integer, Some(Cell::Integer(_))) | (Parsed::Any, Some(_))
+        )
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Parsed::Word => "a word",
+            Parsed::String => "a string",
+            Parsed::Integer => "an integer",
+            Parsed::Any => "a cell",
+        }
+    }
+}
+
+/// Words that take cells from the concat, and the kind of each cell. Their stack effect doesn't include these cells.
+pub const PARSING_WORDS: &[(&str, &[Parsed])] = &[
+    ("@", &[Parsed::Any]), ("def", &[Parsed::Word]), ("var", &[Parsed::Word]), ("lex", &[Parsed::Word]),
+    ("use", &[Parsed::Word]), ("require", &[Parsed::String]), ("forget", &[Parsed::Word]),
+    ("private", &[Parsed::Word]), ("marker", &[Parsed::Word]), ("help", &[Parsed::Word]),
+    ("shuffle", &[Parsed::Integer]), ("?", &[Parsed::Word, Parsed::String, Parsed::String]),
+];
+
+/// Cells that a parsing word takes from the concat, empty if it's not a parsing word.
+pub fn parsed_cells(word: &str) -> &'static [Parsed] {
+    PARSING_WORDS.iter().find(|(w, _)| *w == word).map(|(_, parsed)| *parsed).unwrap_or_default()
+}
+
+/// Words whose effect on the stack or the control flow depends on runtime values, so it can't be inferred.
+const DYNAMIC_WORDS: &[&str] = &[
+    "(", "#(", ")", "wipe", "exe", "either", "skip", "block", "@@", "leave", "loop", "again", "while", "do", "callcc",
+    "rollback", "|",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Stack effect of a word: number of cells it takes from the stack, and number of cells it leaves.
+pub struct Effect {
+    pub inputs: usize,
+    pub outputs: usize,
+}
+
+impl Effect {
+    /// Parse a stack effect comment, like `'a b -> c'`. Returns None if the effect is variable (`'a b c ... N -> z'` or `'? -> ?'`).
+    pub fn parse(effect: &str) -> Option<Self> {
+        let (inputs, outputs) = effect.split_once("->")?;
+        let count = |side: &str| {
+            let mut n = 0;
+            for name in side.split_whitespace() {
+                if name == "?" || name.contains("..") {
+                    return None;
+                }
+                n += 1;
+            }
+            Some(n)
+        };
+        Some(Self { inputs: count(inputs)?, outputs: count(outputs)? })
+    }
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.inputs, self.outputs)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A problem found analyzing the code.
+pub struct Diagnostic {
+    /// Concat position of the cell.
+    pub pos: usize,
+    /// Source location of the cell, if any.
+    pub location: Option<Location>,
+    pub msg: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some(location) => write!(f, "line {}: {}", location.line, self.msg),
+            None => write!(f, "pos {}: {}", self.pos, self.msg),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+/// Checks run by `Pack::code` on the new code, before it's executed.
+pub struct Checks {
+    /// Check the declared stack effects of the defined words, and possible stack underflows.
+    pub effects: bool,
+    /// Check for unknown words, unbalanced blocks, nested stacks and stack transfers, and misused control words.
+    pub lint: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Stack effect of a sequence of cells, while it's being inferred.
+struct Sim {
+    needed: usize,
+    depth: usize,
+}
+
+impl Sim {
+    fn apply(&mut self, effect: Effect) {
+        if self.depth < effect.inputs {
+            self.needed += effect.inputs - self.depth;
+            self.depth = 0;
+        }
+        else {
+            self.depth -= effect.inputs;
+        }
+        self.depth += effect.outputs;
+    }
+
+    fn effect(&self) -> Effect {
+        Effect { inputs: self.needed, outputs: self.depth }
+    }
+}
+
+struct Checker<'a> {
+    pack: &'a Pack,
+    /// Effects of the words documented or defined in the code being checked. None if it's not known.
+    effects: HashMap<String, Option<Effect>>,
+    lex: String,
+    imports: Vec<String>,
+    lex_imports: Vec<usize>,
+    /// Words defined in the code being checked.
+    defined: HashSet<String>,
+    /// Modules required by the code being checked.
+    required: HashSet<String>,
+    /// Local variables visible, with the number of open blocks and nested stacks where they were defined.
+    locals: Vec<(String, usize)>,
+    /// Position of the `}` closing each block, by the position of its `{`.
+    block_ends: HashMap<usize, usize>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    /// Create a checker for the code from concat position `start` to the end.
+    fn new(pack: &'a Pack, start: usize) -> Self {
+        let mut block_ends = HashMap::new();
+        let mut open = Vec::new();
+        for (pos, cell) in pack.concat.array.iter().enumerate().skip(start) {
+            match cell {
+                Cell::Word(w) if w == "{" => open.push(pos),
+                Cell::Word(w) if w == "}" => {
+                    if let Some(block_pos) = open.pop() {
+                        block_ends.insert(block_pos, pos);
+                    }
+                },
+                _ => {},
+            }
+        }
+        Self {
+            pack,
+            effects: HashMap::new(),
+            lex: pack.dictionary.lex.clone(),
+            imports: pack.dictionary.imports.clone(),
+            lex_imports: Vec::new(),
+            defined: HashSet::new(),
+            required: HashSet::new(),
+            locals: Vec::new(),
+            block_ends,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn open_lex(&mut self, lex: &str) {
+        self.lex = self.lex.clone() + lex + ".";
+        self.lex_imports.push(self.imports.len());
+    }
+
+    fn close_lex(&mut self) {
+        self.lex.truncate(self.lex[..self.lex.len().saturating_sub(1)].rfind('.').map(|i| i + 1).unwrap_or(0));
+        if let Some(imports_len) = self.lex_imports.pop() {
+            self.imports.truncate(imports_len);
+        }
+    }
+
+    fn report(&mut self, pos: usize, msg: String) {
+        self.diagnostics.push(Diagnostic { pos, location: self.pack.concat.location(pos), msg });
+    }
+
+    fn cell(&self, pos: usize) -> Option<&'a Cell> {
+        self.pack.concat.array.get(pos)
+    }
+
+    fn word(&self, pos: usize) -> Option<&'a str> {
+        match self.cell(pos) {
+            Some(Cell::Word(w)) => Some(w.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Position of the `}` closing the block opened at `pos`.
+    fn block_end(&self, pos: usize) -> Option<usize> {
+        self.block_ends.get(&pos).copied()
+    }
+
+    /// Effect of a word, looking it up in the current lexicon, its parents and the dictionary.
+    fn word_effect(&self, word: &str, pos: usize) -> Option<Effect> {
+        if DYNAMIC_WORDS.contains(&word) {
+            return None;
+        }
+        let mut prefix = self.lex.as_str();
+        loop {
+            let full_word = String::from(prefix) + word;
+            if let Some(effect) = self.effects.get(&full_word) {
+                return *effect;
+            }
+            if let Some(doc) = self.pack.docs.get(&full_word) {
+                return Effect::parse(&doc.stack);
+            }
+            if let Some(DictEntry::Data(_)) = self.pack.dictionary.dict.get(&full_word) {
+                return Some(Effect { inputs: 0, outputs: 1 });
+            }
+            if prefix.is_empty() {
+                break;
+            }
+            prefix = &prefix[..prefix[..prefix.len() - 1].rfind('.').map(|i| i + 1).unwrap_or(0)];
+        }
+        // Words of imported lexicons
+        let word = self.pack.resolve(word, pos)?;
+        match self.pack.dictionary.dict.get(&*word) {
+            Some(DictEntry::Data(_)) => Some(Effect { inputs: 0, outputs: 1 }),
+            _ => Effect::parse(&self.pack.docs.get(&word)?.stack),
+        }
+    }
+
+    /// Effect of a cell used as a branch of `if`.
+    fn branch_effect(&self, pos: usize) -> Option<Effect> {
+        match self.cell(pos)? {
+            Cell::Word(w) if w == "{" || w == "}" => None,
+            Cell::Word(w) => self.word_effect(w, pos),
+            _ => Some(Effect { inputs: 0, outputs: 1 }),
+        }
+    }
+
+    /// Simulate the cell at `pos`. Returns the position of the next cell, or None if the effect can't be inferred.
+    fn step(&self, sim: &mut Sim, pos: usize) -> Option<usize> {
+        let word = match self.cell(pos)? {
+            Cell::Word(w) => w.as_str(),
+            _ => {
+                sim.apply(Effect { inputs: 0, outputs: 1 });
+                return Some(pos + 1);
+            },
+        };
+        match word {
+            "{" => {
+                sim.apply(Effect { inputs: 0, outputs: 1 });
+                Some(self.block_end(pos)? + 1)
+            },
+            "if" => {
+                let (true_effect, false_effect) = (self.branch_effect(pos + 1)?, self.branch_effect(pos + 2)?);
+                if true_effect != false_effect {
+                    return None;
+                }
+                sim.apply(Effect { inputs: 1, outputs: 0 });
+                sim.apply(true_effect);
+                Some(pos + 3)
+            },
+            "[" => {
+                let (mut inputs, mut outputs, mut transfer) = (0, 0, true);
+                let mut pos = pos + 1;
+                loop {
+                    match self.word(pos)? {
+                        "|" => transfer = false,
+                        "]" => break,
+                        _ if transfer => inputs += 1,
+                        _ => outputs += 1,
+                    }
+                    pos += 1;
+                }
+                sim.apply(Effect { inputs, outputs });
+                Some(pos + 1)
+            },
+            _ => {
+                sim.apply(self.word_effect(word, pos)?);
+                Some(pos + 1 + parsed_cells(word).len())
+            },
+        }
+    }
+
+    /// Position of the cell after the one at `pos`, skipping blocks, stack transfers and the cells taken by parsing words.
+    fn next(&self, pos: usize) -> usize {
+        let len = self.pack.concat.array.len();
+        match self.word(pos) {
+            Some("{") => self.block_end(pos).map(|end| end + 1).unwrap_or(len),
+            Some("[") => self.pack.concat.array[pos..].iter().position(|cell| matches!(cell, Cell::Word(w) if w == "]"))
+                .map(|i| pos + i + 1).unwrap_or(len),
+            Some("if") => pos + 3,
+            Some(word) => pos + 1 + parsed_cells(word).len(),
+            None => pos + 1,
+        }
+    }
+
+    /// Infer the effect of the cells between `start` and `end`.
+    fn infer(&self, start: usize, end: usize) -> Option<Effect> {
+        let mut sim = Sim::default();
+        let mut pos = start;
+        while pos < end {
+            pos = self.step(&mut sim, pos)?;
+        }
+        Some(sim.effect())
+    }
+
+    /// Check a block defined as `word`, against its declared effect.
+    fn check_definition(&mut self, word: &str, block_pos: usize, end: usize) {
+        // The effect documented in the code, or previously in the docs
+        let declared = match self.effects.get(word) {
+            Some(effect) => *effect,
+            None => self.pack.docs.get(word).and_then(|doc| Effect::parse(&doc.stack)),
+        };
+        let declared = match declared {
+            Some(effect) => effect,
+            None => return,
+        };
+        if let Some(effect) = self.infer(block_pos + 1, end) {
+            if effect != declared {
+                self.report(block_pos, format!("Word '{}' declares stack effect {} but its body has {}", word, declared, effect));
+            }
+        }
+    }
+
+    /// Check code from concat position `start` to the end.
+    fn check_code(&mut self, start: usize, stack_size: usize) {
+        let mut sim = Some(Sim { needed: 0, depth: stack_size });
+        let mut pos = start;
+        while pos < self.pack.concat.array.len() {
+            let word = self.word(pos).unwrap_or_default();
+            match word {
+                "?" => {
+                    if let (Some(Cell::Word(doc_word)), Some(Cell::String(stack_effect))) = (self.cell(pos + 1), self.cell(pos + 2)) {
+                        self.effects.insert(self.lex.clone() + doc_word, Effect::parse(stack_effect));
+                    }
+                },
+                "lex" => {
+                    if let Some(lex) = self.word(pos + 1) {
+                        self.open_lex(lex);
+                    }
+                },
+                "\\lex" => self.close_lex(),
+                "{" => {
+                    if let Some(end) = self.block_end(pos) {
+                        if let (Some("def"), Some(name)) = (self.word(end + 1), self.word(end + 2)) {
+                            let name = self.lex.clone() + name;
+                            self.check_definition(&name, pos, end);
+                        }
+                    }
+                },
+                "def" | "var" if pos > start => {
+                    // Data words defined with a literal
+                    if let (Some(cell), Some(name)) = (self.cell(pos - 1), self.word(pos + 1)) {
+                        if !matches!(cell, Cell::Word(w) if w == "}") {
+                            let name = self.lex.clone() + name;
+                            if word == "var" {
+                                self.effects.insert(name.clone() + "!", Some(Effect { inputs: 1, outputs: 0 }));
+                            }
+                            self.effects.insert(name, Some(Effect { inputs: 0, outputs: 1 }));
+                        }
+                    }
+                },
+                _ => {},
+            }
+            // Simulate the top level code, until an effect can't be inferred
+            if let Some(top) = sim.as_mut() {
+                if self.step(top, pos).is_none() {
+                    sim = None;
+                }
+                else if top.needed > 0 {
+                    self.report(pos, format!("Possible stack underflow at '{}', the stack is {} cells short", word, top.needed));
+                    top.needed = 0;
+                }
+            }
+            pos = self.next(pos);
+        }
+    }
+
+    /// Collect the words defined in the code from concat position `start` to the end.
+    fn collect_definitions(&mut self, start: usize) {
+        let lex = self.lex.clone();
+        self.collect_cells(&self.pack.concat.array[start..]);
+        self.lex = lex;
+        self.imports = self.pack.dictionary.imports.clone();
+        self.lex_imports.clear();
+    }
+
+    /// Collect the words defined in `cells`, and in the modules they require.
+    fn collect_cells(&mut self, cells: &[Cell]) {
+        let word = |pos: usize| match cells.get(pos) {
+            Some(Cell::Word(w)) => Some(w.as_str()),
+            _ => None,
+        };
+        for pos in 0..cells.len() {
+            match (word(pos), word(pos + 1)) {
+                (Some("lex"), Some(lex)) => self.open_lex(lex),
+                (Some("\\lex"), _) => self.close_lex(),
+                (Some("def" | "marker"), Some(name)) => {
+                    self.defined.insert(self.lex.clone() + name);
+                },
+                (Some("var"), Some(name)) => {
+                    self.defined.insert(self.lex.clone() + name);
+                    self.defined.insert(self.lex.clone() + name + "!");
+                },
+                // Word references defined with "@def" or given a setter
+                (Some("@"), Some(name)) => match word(pos + 2) {
+                    Some("@def") => {
+                        self.defined.insert(self.lex.clone() + name);
+                    },
+                    Some("setter") => {
+                        self.defined.insert(self.lex.clone() + name + "!");
+                    },
+                    _ => {},
+                },
+                (Some("require"), _) => {
+                    if let Some(Cell::String(name)) = cells.get(pos + 1) {
+                        self.collect_module(name);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    /// Collect the words defined by a required module, getting its code from the module resolver.
+    fn collect_module(&mut self, name: &str) {
+        if self.pack.modules.is_loaded(name) || !self.required.insert(name.into()) {
+            return;
+        }
+        let source = match &self.pack.modules.resolver {
+            Some(resolver) => match resolver.borrow_mut().resolve(name) {
+                Ok(source) => source,
+                Err(_) => return,
+            },
+            None => return,
+        };
+        // The module runs in its own lexicon, without imports
+        let lex = core::mem::replace(&mut self.lex, String::from(name) + ".");
+        let imports = core::mem::take(&mut self.imports);
+        let lex_imports = core::mem::take(&mut self.lex_imports);
+        self.collect_cells(&self.pack.tokenize(&source));
+        (self.lex, self.imports, self.lex_imports) = (lex, imports, lex_imports);
+    }
+
+    /// Check if a word exists in the dictionary or is defined in the code being checked.
+    fn is_known(&self, word: &str, pos: usize) -> bool {
+        if word == "$" || self.pack.resolve(word, pos).is_some() {
+            return true;
+        }
+        let local = word.strip_suffix('!').unwrap_or(word);
+        if self.locals.iter().any(|(name, _)| name == local) {
+            return true;
+        }
+        let mut prefix = self.lex.as_str();
+        loop {
+            let full_word = String::from(prefix) + word;
+            if self.defined.contains(&full_word) || self.pack.dictionary.dict.contains_key(&full_word) {
+                return true;
+            }
+            if prefix.is_empty() {
+                break;
+            }
+            prefix = &prefix[..prefix[..prefix.len() - 1].rfind('.').map(|i| i + 1).unwrap_or(0)];
+        }
+        self.imports.iter().any(|prefix| {
+            let full_word = prefix.clone() + word;
+            self.defined.contains(&full_word) || self.pack.dictionary.dict.contains_key(&full_word)
+        })
+    }
+
+    fn check_known(&mut self, pos: usize) {
+        if let Some(word) = self.word(pos) {
+            if !self.is_known(word, pos) {
+                self.report(pos, format!("Unknown word '{}'", word));
+            }
+        }
+    }
+
+    /// Lint the code from concat position `start` to the end.
+    fn lint_code(&mut self, start: usize) {
+        self.collect_definitions(start);
+        // Open blocks and nested stacks, and the number of loops open in each block
+        let mut open: Vec<(&str, usize)> = Vec::new();
+        let mut loops: Vec<usize> = vec![0];
+        let len = self.pack.concat.array.len();
+        let mut pos = start;
+        while pos < len {
+            let word = match self.word(pos) {
+                Some(word) => word,
+                None => {
+                    pos += 1;
+                    continue;
+                },
+            };
+            match word {
+                "{" | "(" | "#(" => {
+                    open.push((word, pos));
+                    if word == "{" {
+                        loops.push(0);
+                    }
+                },
+                "}" | ")" => {
+                    let opener = if word == "}" { "{" } else { "(" };
+                    match open.pop() {
+                        Some((w, _)) if w == opener || (w == "#(" && word == ")") => {
+                            if word == "}" {
+                                loops.pop();
+                            }
+                            let level = open.len();
+                            self.locals.retain(|(_, local_level)| *local_level <= level);
+                        },
+                        Some((w, open_pos)) => {
+                            self.report(pos, format!("'{}' closes '{}' opened at {}", word, w, self.position(open_pos)));
+                            open.push((w, open_pos));
+                        },
+                        None => self.report(pos, format!("'{}' without matching '{}'", word, opener)),
+                    }
+                },
+                "[" => {
+                    let end = self.pack.concat.array[pos..].iter().position(|cell| matches!(cell, Cell::Word(w) if w == "]"));
+                    match end {
+                        Some(end) => {
+                            let transfer = &self.pack.concat.array[pos + 1..pos + end];
+                            if !transfer.iter().any(|cell| matches!(cell, Cell::Word(w) if w == "|")) {
+                                self.report(pos, "Stack transfer without '|'".into());
+                            }
+                            if let Some(i) = transfer.iter().position(|cell| !matches!(cell, Cell::Word(_))) {
+                                self.report(pos + 1 + i, "Stack transfer names must be words".into());
+                            }
+                            pos += end + 1;
+                        },
+                        None => {
+                            self.report(pos, "'[' without matching ']'".into());
+                            pos = len;
+                        },
+                    }
+                    continue;
+                },
+                // Local variables of a block
+                "|" if pos > 0 && self.word(pos - 1) == Some("{") => {
+                    let end = self.pack.concat.array[pos + 1..].iter().position(|cell| !matches!(cell, Cell::Word(w) if w != "|"));
+                    match end {
+                        Some(end) if self.word(pos + 1 + end) == Some("|") => {
+                            for name_pos in pos + 1..pos + 1 + end {
+                                let name = self.word(name_pos).unwrap_or_default();
+                                self.locals.push((name.into(), open.len()));
+                            }
+                            pos += end + 2;
+                        },
+                        _ => {
+                            self.report(pos, "Local variable names must be words, followed by '|'".into());
+                            pos += 1;
+                        },
+                    }
+                    continue;
+                },
+                "]" | "|" => self.report(pos, format!("'{}' outside a stack transfer", word)),
+                "lex" => {
+                    if let Some(lex) = self.word(pos + 1) {
+                        self.open_lex(lex);
+                    }
+                },
+                "\\lex" => self.close_lex(),
+                "use" => {
+                    if let Some(lex) = self.word(pos + 1) {
+                        self.imports.push(String::from(lex) + ".");
+                    }
+                },
+                "if" => {
+                    for branch in [pos + 1, pos + 2] {
+                        if self.cell(branch).is_none() || matches!(self.word(branch), Some("{" | "}" | "(" | "#(" | ")")) {
+                            self.report(pos, "'if' must be followed by two words or values, for true and false".into());
+                            break;
+                        }
+                        self.check_known(branch);
+                    }
+                    pos += 3;
+                    continue;
+                },
+                "while" => {
+                    if self.word(pos + 2) != Some("do") {
+                        self.report(pos, "'while' must be followed by a condition word, 'do' and an action word".into());
+                    }
+                },
+                "do" => {
+                    if pos < 2 || self.word(pos - 2) != Some("while") {
+                        self.report(pos, "'do' must be preceded by 'while' and a condition word".into());
+                    }
+                    if self.word(pos + 1).is_none() {
+                        self.report(pos, "'do' must be followed by an action word".into());
+                    }
+                },
+                "loop" => {
+                    if let Some(n) = loops.last_mut() {
+                        *n += 1;
+                    }
+                },
+                "again" => {
+                    if loops.last().copied().unwrap_or(0) == 0 {
+                        self.report(pos, "'again' without a 'loop' in the same block".into());
+                    }
+                },
+                _ => self.check_known(pos),
+            }
+            // Cells taken from the concat by parsing words are not executed
+            let parsed = parsed_cells(word);
+            if parsed.iter().enumerate().any(|(i, kind)| !kind.accepts(self.cell(pos + 1 + i))) {
+                let names: Vec<&str> = parsed.iter().map(|kind| kind.name()).collect();
+                let expected = match names.split_last() {
+                    Some((last, [])) => String::from(*last),
+                    Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+                    None => String::new(),
+                };
+                self.report(pos, format!("'{}' must be followed by {}", word, expected));
+            }
+            pos += parsed.len();
+            pos += 1;
+        }
+        for (w, open_pos) in open {
+            self.report(open_pos, format!("'{}' is never closed", w));
+        }
+    }
+
+    /// Description of a concat position for messages: the line, if known.
+    fn position(&self, pos: usize) -> String {
+        match self.pack.concat.location(pos) {
+            Some(location) => format!("line {}", location.line),
+            None => format!("pos {}", pos),
+        }
+    }
+}
+
+/// Check the stack effects of the code from concat position `start` to the end, before running it.
+///
+/// Defined words with a documented stack effect (using `?`) are checked against the effect inferred from their body.
+/// The top level code is checked for possible stack underflows, assuming the current stack. The inference stops at
+/// words with unknown or variable effects.
+pub fn check_effects(pack: &Pack, start: usize) -> Vec<Diagnostic> {
+    let mut checker = Checker::new(pack, start);
+    checker.check_code(start, pack.stack.size());
+    checker.diagnostics
+}
+
+/// Infer the stack effect of a defined word from its body. Returns None if it can't be inferred.
+pub fn infer_effect(pack: &Pack, word: &str) -> Option<Effect> {
+    if let Some(DictEntry::Defined(block)) = pack.dictionary.dict.get(word) {
+        let checker = Checker::new(pack, block.pos);
+        checker.infer(block.pos, block.pos + block.len - 1)
+    }
+    else {
+        None
+    }
+}
+
+/// Lint the code from concat position `start` to the end, before running it.
+///
+/// Reports words that are not in the dictionary, defined in the code nor local variables, unbalanced blocks, nested stacks and stack
+/// transfers, and misused control words (`if`, `while`, `do` and `again`).
+pub fn lint(pack: &Pack, start: usize) -> Vec<Diagnostic> {
+    let mut checker = Checker::new(pack, start);
+    checker.lint_code(start);
+    checker.diagnostics
+}
@@ -0,0 +1,374 @@
+use super::core::{Pack, Cell, Error, Quotation, Trampolines};
+use super::primitives::close_curly;
+use alloc::{vec::Vec, string::String};
+use core::cmp::Ordering;
+
+pub fn register_combinators(pack: &mut Pack) -> Result<(), Error> {
+    // Multi-word names use underscores, like "lex_words" or "list.index_of", so it's "sort_by" and not "sort-by"
+    pack.def_natives(&[
+        ("each", each), ("map", map), ("filter", filter), ("fold", fold), ("reduce", reduce), ("any", any),
+        ("all", all), ("sort_by", sort_by), (">r", to_retain), ("r>", from_retain), ("dip", dip), ("keep", keep),
+        ("bi", bi), ("tri", tri), ("bi*", bi_star), ("bi@", bi_at), ("cleave", cleave), ("curry", curry),
+        ("compose", compose),
+    ])?;
+    // The trampolines use internal words, so scripts can't redefine them and the sandbox doesn't block them
+    let from_retain = pack.dictionary.internal("r>", from_retain);
+    let exe = pack.dictionary.internal("exe", exe);
+    let cleave = pack.dictionary.internal("cleave", cleave);
+    let ret = pack.dictionary.internal("}", close_curly);
+    let mut block = |words: &[&String]| pack.append_block(words.iter().map(|w| Cell::Word((*w).clone())).collect());
+    let trampolines = Trampolines {
+        restore: block(&[&from_retain, &ret]),
+        apply: block(&[&from_retain, &from_retain, &exe, &ret]),
+        cleave: block(&[&from_retain, &from_retain, &cleave, &ret]),
+        call: block(&[&from_retain, &exe, &ret]),
+    };
+    pack.trampolines = trampolines;
+    Ok(())
+}
+
+/// Sequence a combinator operates on: a list, or all the cells of the current stack.
+struct Seq {
+    cells: Vec<Cell>,
+    is_list: bool,
+}
+
+impl Seq {
+    /// Put the result back: as a list if the sequence was a list, or as cells of the current stack.
+    fn push(self, pack: &mut Pack) {
+        if self.is_list {
+            pack.stack.push(self.cells.into());
+        }
+        else {
+            for cell in self.cells {
+                pack.stack.push(cell);
+            }
+        }
+    }
+}
+
+fn pop_block(pack: &mut Pack, word: &str) -> Result<Cell, Error> {
+    match pack.stack.pop() {
+        Some(cell @ (Cell::Block(_) | Cell::Closure(_) | Cell::Quotation(_) | Cell::Word(_))) => Ok(cell),
+        _ => Err(Error::new(format!("{}: Couldn't get a block from the stack", word))),
+    }
+}
+
+fn pop_seq(pack: &mut Pack) -> Seq {
+    if let Some(Cell::List(_)) = pack.stack.get(0) {
+        if let Some(Cell::List(cells)) = pack.stack.pop() {
+            return Seq { cells, is_list: true };
+        }
+    }
+    Seq { cells: pack.stack.take(), is_list: false }
+}
+
+/// Call `block` in a new nested stack with `args`, and return the cells it leaves. Returns `None` if the block escaped
+/// with `leave` or a continuation, then the combinator must stop.
+fn apply(pack: &mut Pack, word: &str, block: &Cell, args: impl IntoIterator<Item = Cell>) -> Result<Option<Vec<Cell>>, Error> {
+    let level = pack.stack.level();
+    pack.stack.start_stack();
+    for arg in args {
+        pack.stack.push(arg);
+    }
+    let result = pack.call(block.clone());
+    match result {
+        Ok(true) if pack.stack.level() == level + 1 => {
+            let results = pack.stack.take();
+            pack.stack.end_stack();
+            return Ok(Some(results));
+        },
+        Ok(false) => {
+            // Close the nested stacks, the cells left by the block stay in the stack like with any other escape
+            while pack.stack.level() > level {
+                pack.stack.end_stack();
+            }
+            return Ok(None);
+        },
+        _ => {},
+    }
+    // Discard the nested stack of the block, and any other left open
+    while pack.stack.level() > level {
+        pack.stack.take();
+        pack.stack.end_stack();
+    }
+    result?;
+    Err(Error::new(format!("{}: The block must close the nested stacks it opens", word)))
+}
+
+/// Call `block` and get the single cell it leaves.
+fn apply_one(pack: &mut Pack, word: &str, block: &Cell, args: impl IntoIterator<Item = Cell>) -> Result<Option<Cell>, Error> {
+    let mut results = match apply(pack, word, block, args)? {
+        Some(results) => results,
+        None => return Ok(None),
+    };
+    match (results.pop(), results.is_empty()) {
+        (Some(cell), true) => Ok(Some(cell)),
+        _ => Err(Error::new(format!("{}: The block must leave one cell", word))),
+    }
+}
+
+/// Call `block` and get the boolean it leaves.
+fn apply_bool(pack: &mut Pack, word: &str, block: &Cell, arg: Cell) -> Result<Option<bool>, Error> {
+    match apply_one(pack, word, block, [arg])? {
+        Some(Cell::Boolean(b)) => Ok(Some(b)),
+        Some(_) => Err(Error::new(format!("{}: The block must leave a boolean", word))),
+        None => Ok(None),
+    }
+}
+
+fn each(pack: &mut Pack) -> Result<bool, Error> {
+    let block = pop_block(pack, "each")?;
+    for cell in pop_seq(pack).cells {
+        let results = match apply(pack, "each", &block, [cell])? {
+            Some(results) => results,
+            None => return Ok(true),
+        };
+        for result in results {
+            pack.stack.push(result);
+        }
+    }
+    Ok(true)
+}
+
+fn map(pack: &mut Pack) -> Result<bool, Error> {
+    let block = pop_block(pack, "map")?;
+    let seq = pop_seq(pack);
+    let mut cells = Vec::with_capacity(seq.cells.len());
+    for cell in seq.cells {
+        let results = match apply(pack, "map", &block, [cell])? {
+            Some(results) => results,
+            None => return Ok(true),
+        };
+        cells.extend(results);
+    }
+    Seq { cells, is_list: seq.is_list }.push(pack);
+    Ok(true)
+}
+
+fn filter(pack: &mut Pack) -> Result<bool, Error> {
+    let block = pop_block(pack, "filter")?;
+    let seq = pop_seq(pack);
+    let mut cells = Vec::new();
+    for cell in seq.cells {
+        match apply_bool(pack, "filter", &block, cell.clone())? {
+            Some(true) => cells.push(cell),
+            Some(false) => {},
+            None => return Ok(true),
+        }
+    }
+    Seq { cells, is_list: seq.is_list }.push(pack);
+    Ok(true)
+}
+
+fn fold(pack: &mut Pack) -> Result<bool, Error> {
+    let block = pop_block(pack, "fold")?;
+    let mut acc = pack.stack.pop().ok_or_else(|| Error::new("fold: Couldn't get the initial value from the stack".into()))?;
+    for cell in pop_seq(pack).cells {
+        acc = match apply_one(pack, "fold", &block, [acc, cell])? {
+            Some(acc) => acc,
+            None => return Ok(true),
+        };
+    }
+    pack.stack.push(acc);
+    Ok(true)
+}
+
+fn reduce(pack: &mut Pack) -> Result<bool, Error> {
+    let block = pop_block(pack, "reduce")?;
+    let mut cells = pop_seq(pack).cells.into_iter();
+    let mut acc = cells.next().ok_or_else(|| Error::new("reduce: The sequence is empty".into()))?;
+    for cell in cells {
+        acc = match apply_one(pack, "reduce", &block, [acc, cell])? {
+            Some(acc) => acc,
+            None => return Ok(true),
+        };
+    }
+    pack.stack.push(acc);
+    Ok(true)
+}
+
+fn any(pack: &mut Pack) -> Result<bool, Error> {
+    let block = pop_block(pack, "any")?;
+    let mut found = false;
+    for cell in pop_seq(pack).cells {
+        match apply_bool(pack, "any", &block, cell)? {
+            Some(true) => {
+                found = true;
+                break;
+            },
+            Some(false) => {},
+            None => return Ok(true),
+        }
+    }
+    pack.stack.push(found.into());
+    Ok(true)
+}
+
+fn all(pack: &mut Pack) -> Result<bool, Error> {
+    let block = pop_block(pack, "all")?;
+    let mut holds = true;
+    for cell in pop_seq(pack).cells {
+        match apply_bool(pack, "all", &block, cell)? {
+            Some(false) => {
+                holds = false;
+                break;
+            },
+            Some(true) => {},
+            None => return Ok(true),
+        }
+    }
+    pack.stack.push(holds.into());
+    Ok(true)
+}
+
+fn sort_by(pack: &mut Pack) -> Result<bool, Error> {
+    let block = pop_block(pack, "sort_by")?;
+    let seq = pop_seq(pack);
+    let mut keyed = Vec::with_capacity(seq.cells.len());
+    for cell in seq.cells {
+        let key = match apply_one(pack, "sort_by", &block, [cell.clone()])? {
+            Some(key) => key,
+            None => return Ok(true),
+        };
+        keyed.push((key, cell));
+    }
+    if keyed.windows(2).any(|pair| pair[0].0.partial_cmp(&pair[1].0).is_none()) {
+        return Err(Error::new("sort_by: The keys can't be compared".into()));
+    }
+    // Stable sort, cells with equal keys keep their order
+    keyed.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let cells = keyed.into_iter().map(|(_, cell)| cell).collect();
+    Seq { cells, is_list: seq.is_list }.push(pack);
+    Ok(true)
+}
+
+fn pop_cell(pack: &mut Pack, word: &str) -> Result<Cell, Error> {
+    pack.stack.pop().ok_or_else(|| Error::new(format!("{}: Stack is empty", word)))
+}
+
+fn to_retain(pack: &mut Pack) -> Result<bool, Error> {
+    let cell = pop_cell(pack, ">r")?;
+    pack.retain.push(cell);
+    Ok(true)
+}
+
+fn from_retain(pack: &mut Pack) -> Result<bool, Error> {
+    let cell = pack.retain.pop().ok_or_else(|| Error::new("r>: Retain stack is empty".into()))?;
+    pack.stack.push(cell);
+    Ok(true)
+}
+
+/// Execute the cell taken from the stack, like `exe`.
+fn exe(pack: &mut Pack) -> Result<bool, Error> {
+    let cell = pop_cell(pack, "exe")?;
+    pack.jump(cell)
+}
+
+fn dip(pack: &mut Pack) -> Result<bool, Error> {
+    let block = pop_block(pack, "dip")?;
+    let cell = pop_cell(pack, "dip")?;
+    pack.retain.push(cell);
+    pack.jump_then(block, pack.trampolines.restore)
+}
+
+fn keep(pack: &mut Pack) -> Result<bool, Error> {
+    let block = pop_block(pack, "keep")?;
+    let cell = pop_cell(pack, "keep")?;
+    pack.retain.push(cell.clone());
+    pack.stack.push(cell);
+    pack.jump_then(block, pack.trampolines.restore)
+}
+
+/// Execute each block of `blocks` with a copy of `cell`.
+fn cleave_blocks(pack: &mut Pack, cell: Cell, mut blocks: Vec<Cell>) -> Result<bool, Error> {
+    if blocks.is_empty() {
+        return Ok(true);
+    }
+    let first = blocks.remove(0);
+    pack.stack.push(cell.clone());
+    if blocks.is_empty() {
+        return pack.jump(first);
+    }
+    // The rest of the blocks are executed when the first returns
+    pack.retain.push(blocks.into());
+    pack.retain.push(cell);
+    pack.jump_then(first, pack.trampolines.cleave)
+}
+
+fn bi(pack: &mut Pack) -> Result<bool, Error> {
+    let q = pop_block(pack, "bi")?;
+    let p = pop_block(pack, "bi")?;
+    let cell = pop_cell(pack, "bi")?;
+    cleave_blocks(pack, cell, vec![p, q])
+}
+
+fn tri(pack: &mut Pack) -> Result<bool, Error> {
+    let r = pop_block(pack, "tri")?;
+    let q = pop_block(pack, "tri")?;
+    let p = pop_block(pack, "tri")?;
+    let cell = pop_cell(pack, "tri")?;
+    cleave_blocks(pack, cell, vec![p, q, r])
+}
+
+fn cleave(pack: &mut Pack) -> Result<bool, Error> {
+    let blocks = match pack.stack.pop() {
+        Some(Cell::List(blocks)) => blocks,
+        _ => return Err(Error::new("cleave: Couldn't get a list of blocks from the stack".into())),
+    };
+    let cell = pop_cell(pack, "cleave")?;
+    cleave_blocks(pack, cell, blocks)
+}
+
+/// Execute block `p` with cell `a`, and then block `q` with cell `b`.
+fn apply_two(pack: &mut Pack, a: Cell, p: Cell, b: Cell, q: Cell) -> Result<bool, Error> {
+    pack.retain.push(q);
+    pack.retain.push(b);
+    pack.stack.push(a);
+    pack.jump_then(p, pack.trampolines.apply)
+}
+
+fn bi_star(pack: &mut Pack) -> Result<bool, Error> {
+    let q = pop_block(pack, "bi*")?;
+    let p = pop_block(pack, "bi*")?;
+    let b = pop_cell(pack, "bi*")?;
+    let a = pop_cell(pack, "bi*")?;
+    apply_two(pack, a, p, b, q)
+}
+
+fn bi_at(pack: &mut Pack) -> Result<bool, Error> {
+    let q = pop_block(pack, "bi@")?;
+    let b = pop_cell(pack, "bi@")?;
+    let a = pop_cell(pack, "bi@")?;
+    apply_two(pack, a, q.clone(), b, q)
+}
+
+fn curry(pack: &mut Pack) -> Result<bool, Error> {
+    let block = pop_block(pack, "curry")?;
+    let cell = pop_cell(pack, "curry")?;
+    let quot = match block {
+        Cell::Quotation(mut quot) => {
+            quot.args.insert(0, cell);
+            quot
+        },
+        block => Quotation { args: vec![cell], calls: vec![block] },
+    };
+    pack.stack.push(quot.into());
+    Ok(true)
+}
+
+fn compose(pack: &mut Pack) -> Result<bool, Error> {
+    let q = pop_block(pack, "compose")?;
+    let p = pop_block(pack, "compose")?;
+    let mut quot = match p {
+        Cell::Quotation(quot) => quot,
+        p => Quotation { args: Vec::new(), calls: vec![p] },
+    };
+    match q {
+        // The arguments of "q" must be pushed after "p" runs, so it's kept as a whole
+        Cell::Quotation(q) if q.args.is_empty() => quot.calls.extend(q.calls),
+        q => quot.calls.push(q),
+    }
+    pack.stack.push(quot.into());
+    Ok(true)
+}
@@ -1,9 +1,12 @@
-use hashbrown::HashMap;
-use alloc::{boxed::Box, vec::Vec, string::String, format, str};
-use core::hash::Hash;
+use hashbrown::{HashMap, HashSet};
+use alloc::{boxed::Box, vec::Vec, string::String, format, str, rc::Rc, borrow::Cow};
+use core::{hash::Hash, cell::RefCell};
 use super::primitives::register_primitives;
 use super::prelude::PRELUDE;
 use super::run_future::RunFuture;
+use super::modules::Modules;
+use super::docs::Docs;
+use super::analysis::{Checks, Diagnostic, check_effects, lint};
 
 #[derive(Debug)]
 /// Error type
@@ -17,7 +20,7 @@ impl Error {
     }
 }
 
-#[derive(PartialEq, PartialOrd, Eq, Hash, Clone, Copy, Debug)]
+#[derive(Default, PartialEq, PartialOrd, Eq, Hash, Clone, Copy, Debug)]
 /// Block reference type
 pub struct BlockRef {
     pub pos: usize,
@@ -76,9 +79,13 @@ pub trait StructCell: core::fmt::Debug {
     /// Clone wrapper.
     fn object_clone(&self) -> Box<dyn StructCell>;
     /// Execute a command.
-    fn doit(&self, cmd: &str, args: Option<Vec<Cell>>) -> ExtOption;
+    fn doit(&self, cmd: &str, args: Option<Vec<Cell>>) -> ExtOption<'_>;
     /// Execute a command in a mutable instance.
-    fn doit_mut(&mut self, cmd: &str, args: Option<Vec<Cell>>) -> ExtOption;
+    fn doit_mut(&mut self, cmd: &str, args: Option<Vec<Cell>>) -> ExtOption<'_>;
+    /// Text representation, used by `>str` and `fmt`. If None, the struct name is used.
+    fn display(&self) -> Option<String> {
+        None
+    }
 }
 
 #[derive(Debug)]
@@ -108,6 +115,126 @@ impl Clone for Struct {
     }
 }
 
+#[derive(PartialEq, PartialOrd, Clone, Debug)]
+/// Continuation, the captured state of execution that can be resumed later.
+pub struct Continuation {
+    /// Concat pointer where execution resumes.
+    pub pointer: usize,
+    /// Return stack at the moment of the capture.
+    pub ret: RetStack,
+    /// Retain stack at the moment of the capture, with the cells kept by the combinators in progress. It's shared,
+    /// to keep the cells small.
+    pub retain: Rc<Stack>,
+}
+
+#[derive(PartialEq, PartialOrd, Clone, Debug)]
+/// Closure, a block with the local variables captured when it was created.
+pub struct Closure {
+    pub block: BlockRef,
+    /// Number of cells at the beginning of the block skipped when executing it (the local variables definition).
+    pub skip: usize,
+    pub names: Rc<Vec<String>>,
+    pub values: Vec<Cell>,
+}
+
+#[derive(PartialEq, PartialOrd, Clone, Debug)]
+/// Quotation, created by `curry` and `compose`: pushes some cells into the stack and then executes others.
+pub struct Quotation {
+    /// Cells pushed into the stack.
+    pub args: Vec<Cell>,
+    /// Blocks, closures, quotations or words executed in order.
+    pub calls: Vec<Cell>,
+}
+
+#[derive(Default, Clone, Copy, Debug)]
+/// Blocks that the combinators execute when the code they call returns, to continue with the rest of their work.
+pub struct Trampolines {
+    /// Get a cell from the retain stack: `r> }`.
+    pub restore: BlockRef,
+    /// Get a cell and a block from the retain stack, and execute the block: `r> r> exe }`.
+    pub apply: BlockRef,
+    /// Get a cell and a list of blocks from the retain stack, and cleave: `r> r> cleave }`.
+    pub cleave: BlockRef,
+    /// Get a block from the retain stack, and execute it: `r> exe }`.
+    pub call: BlockRef,
+}
+
+#[derive(Default, PartialEq, Clone, Debug)]
+/// Map, a hash table of cells.
+///
+/// Floats are valid keys, except NaN, and `0.0` and `-0.0` are the same key. Structs, continuations, closures,
+/// quotations and maps can't be keys, and neither can lists that contain them.
+pub struct Map {
+    entries: HashMap<Cell, Cell>,
+}
+
+impl PartialOrd for Map {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        // Maps are not ordered, they can only be equal
+        if self == other { Some(core::cmp::Ordering::Equal) } else { None }
+    }
+}
+
+impl Map {
+    /// Check if a cell can be used as a key.
+    pub fn is_key(cell: &Cell) -> bool {
+        match cell {
+            Cell::Float(f) => !f.is_nan(),
+            Cell::Struct(_) | Cell::Continuation(_) | Cell::Closure(_) | Cell::Quotation(_) | Cell::Map(_) => false,
+            Cell::List(list) => list.iter().all(Self::is_key),
+            _ => true,
+        }
+    }
+
+    /// Set the value of a key. Returns false if the cell can't be a key.
+    pub fn insert(&mut self, key: Cell, value: Cell) -> bool {
+        if Self::is_key(&key) {
+            self.entries.insert(key, value);
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    /// Get the value of a key.
+    pub fn get(&self, key: &Cell) -> Option<&Cell> {
+        self.entries.get(key)
+    }
+
+    /// Check if a key exists.
+    pub fn contains_key(&self, key: &Cell) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Remove a key, returning its value.
+    pub fn remove(&mut self, key: &Cell) -> Option<Cell> {
+        self.entries.remove(key)
+    }
+
+    /// Number of entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the map has no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All entries, sorted by key.
+    pub fn entries(&self) -> Vec<(&Cell, &Cell)> {
+        let mut entries: Vec<(&Cell, &Cell)> = self.entries.iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(core::cmp::Ordering::Equal));
+        entries
+    }
+
+    /// Add all entries of another map, replacing the existing keys.
+    pub fn merge(&mut self, other: Map) {
+        self.entries.extend(other.entries);
+    }
+}
+
 #[derive(PartialEq, PartialOrd, Clone, Debug)]
 /// Data primitive
 pub enum Cell {
@@ -118,6 +245,11 @@ pub enum Cell {
     Word(String),
     Block(BlockRef),
     Struct(Struct),
+    Continuation(Continuation),
+    Closure(Closure),
+    Quotation(Quotation),
+    List(Vec<Cell>),
+    Map(Map),
 }
 
 impl Cell {
@@ -150,6 +282,24 @@ impl Cell {
 impl Hash for Cell {
     fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
         core::mem::discriminant(self).hash(state);
+        match self {
+            Cell::Integer(i) => i.hash(state),
+            // 0.0 and -0.0 are equal, so they must have the same hash
+            Cell::Float(f) => if *f == 0.0 { 0.0f64.to_bits().hash(state) } else { f.to_bits().hash(state) },
+            Cell::Boolean(b) => b.hash(state),
+            Cell::String(s) | Cell::Word(s) => s.hash(state),
+            Cell::Block(block) => block.hash(state),
+            // Structs are equal when they have the same name
+            Cell::Struct(s) => s.name.hash(state),
+            Cell::Continuation(cont) => cont.pointer.hash(state),
+            Cell::Closure(closure) => closure.block.hash(state),
+            Cell::Quotation(quot) => {
+                quot.args.hash(state);
+                quot.calls.hash(state);
+            },
+            Cell::List(list) => list.hash(state),
+            Cell::Map(map) => map.len().hash(state),
+        }
     }
 }
 
@@ -197,47 +347,430 @@ impl From<Struct> for Cell {
     }
 }
 
+impl From<Continuation> for Cell {
+    fn from(val: Continuation) -> Self {
+        Cell::Continuation(val)
+    }
+}
+
+impl From<Closure> for Cell {
+    fn from(val: Closure) -> Self {
+        Cell::Closure(val)
+    }
+}
+
+impl From<Quotation> for Cell {
+    fn from(val: Quotation) -> Self {
+        Cell::Quotation(val)
+    }
+}
+
+impl From<Vec<Cell>> for Cell {
+    fn from(val: Vec<Cell>) -> Self {
+        Cell::List(val)
+    }
+}
+
+impl From<Map> for Cell {
+    fn from(val: Map) -> Self {
+        Cell::Map(val)
+    }
+}
+
 //TODO: Make native words async, or how to interact with async functions
 
+/// Native word function
+pub type NativeFn = fn(&mut Pack) -> Result<bool, Error>;
+
 #[derive(Clone)]
 /// Dictionary entry
 pub enum DictEntry {
-    Native(fn(&mut Pack) -> Result<bool, Error>),
+    Native(NativeFn),
     Defined(BlockRef),
     Data(Cell),
 }
 
+impl DictEntry {
+    /// Name of the entry kind: "native", "defined" or "data".
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DictEntry::Native(_) => "native",
+            DictEntry::Defined(_) => "defined",
+            DictEntry::Data(_) => "data",
+        }
+    }
+}
+
+/// Scope index of the cells that run in the current scope, like those appended without a scope.
+pub const UNSCOPED: usize = 0;
+/// Scope index of the global scope: no lexicon and no imports.
+pub const GLOBAL_SCOPE: usize = 1;
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// Lexical scope where a block was created: the lexicon prefix and the imported lexicons.
+pub struct Scope {
+    pub lex: String,
+    pub imports: Vec<String>,
+}
+
 #[derive(Default, Clone)]
 /// Dictionary of words
 pub struct Dictionary {
     pub dict: HashMap<String, DictEntry>,
     pub lex: String,
+    /// Imported lexicon prefixes, searched when executing a word.
+    pub imports: Vec<String>,
+    /// Scopes of the cells, referenced by `Concat::scopes`. Indexes `UNSCOPED` and `GLOBAL_SCOPE` are reserved.
+    pub scopes: Vec<Scope>,
+    lex_imports: Vec<usize>,
+    sealed: HashSet<String>,
+    sealed_lex: Vec<String>,
+    private: HashSet<String>,
+    markers: Vec<Marker>,
+    optimized: HashMap<String, Optimized>,
+    internal: HashMap<String, NativeFn>,
+    /// Buffer to build the prefixed names while resolving a word.
+    resolve_buffer: RefCell<String>,
+}
+
+/// Prefix of the internal words. The parser never produces it, so scripts can't redefine or shadow them.
+pub const INTERNAL: char = '\u{0}';
+
+#[derive(Debug, Clone, PartialEq)]
+/// An optimized defined word.
+pub struct Optimized {
+    /// Block of the original definition.
+    pub original: BlockRef,
+    /// Block of the optimized definition.
+    pub block: BlockRef,
+    /// Words whose definition was used to optimize it, like inlined words.
+    pub deps: Vec<String>,
+}
+
+#[derive(Default, Clone)]
+/// Dictionary state saved by a marker: the previous entry of every word changed after it.
+struct Marker {
+    name: String,
+    saved: HashMap<String, Option<DictEntry>>,
+    /// Private words when the marker was created.
+    private: HashSet<String>,
 }
 
 impl Dictionary {
+    /// Define a word with its full name. Fails if the word is sealed.
+    pub fn insert(&mut self, word: String, entry: DictEntry) -> Result<(), Error> {
+        if self.is_sealed(&word) {
+            Err(Error::new(format!("Word '{}' is sealed and can't be redefined", word)))
+        }
+        else {
+            self.save(&word);
+            self.invalidate(&word);
+            self.dict.insert(word, entry);
+            Ok(())
+        }
+    }
+
+    /// Remove a word. Fails if the word doesn't exist or is sealed.
+    pub fn forget(&mut self, word: &str) -> Result<(), Error> {
+        if self.is_sealed(word) {
+            Err(Error::new(format!("Word '{}' is sealed and can't be removed", word)))
+        }
+        else if !self.dict.contains_key(word) {
+            Err(Error::new(format!("Word '{}' doesn't exist in dictionary", word)))
+        }
+        else {
+            self.save(word);
+            self.invalidate(word);
+            self.dict.remove(word);
+            self.private.remove(word);
+            Ok(())
+        }
+    }
+
+    /// Create a marker, recording the current state of the dictionary.
+    pub fn marker(&mut self, name: &str) {
+        self.markers.push(Marker { name: name.into(), saved: HashMap::new(), private: self.private.clone() });
+    }
+
+    /// Roll back the dictionary to the state it had when the marker was created, restoring any shadowed definition.
+    /// The marker and all markers created after it are removed. Sealed words are kept.
+    pub fn rollback(&mut self, name: &str) -> Result<(), Error> {
+        if let Some(index) = self.markers.iter().rposition(|marker| marker.name == name) {
+            // Undo the markers from the newest, each one restores the state at its creation
+            while self.markers.len() > index {
+                if let Some(marker) = self.markers.pop() {
+                    // Sealed words are kept, and so is their visibility
+                    let sealed_private = self.private.iter().filter(|word| self.is_sealed(word)).cloned().collect::<Vec<_>>();
+                    self.private = marker.private;
+                    self.private.extend(sealed_private);
+                    for (word, entry) in marker.saved {
+                        if self.is_sealed(&word) {
+                            continue;
+                        }
+                        self.invalidate(&word);
+                        match entry {
+                            Some(entry) => self.dict.insert(word, entry),
+                            None => self.dict.remove(&word),
+                        };
+                    }
+                }
+            }
+            Ok(())
+        }
+        else {
+            Err(Error::new(format!("Marker '{}' doesn't exist", name)))
+        }
+    }
+
+    /// Replace the definition of a word with an optimized block. Fails if the word is sealed. Rolling back a marker
+    /// created before restores the original definition.
+    pub fn set_optimized(&mut self, word: &str, optimized: Optimized) -> Result<(), Error> {
+        if self.is_sealed(word) {
+            return Err(Error::new(format!("Word '{}' is sealed and can't be optimized", word)));
+        }
+        self.save(word);
+        self.dict.insert(word.into(), DictEntry::Defined(optimized.block));
+        self.optimized.insert(word.into(), optimized);
+        Ok(())
+    }
+
+    /// Get the optimization of a word, if it's optimized.
+    pub fn optimized(&self, word: &str) -> Option<&Optimized> {
+        self.optimized.get(word)
+    }
+
+    /// Restore the original definition of an optimized word.
+    pub fn deoptimize(&mut self, word: &str) {
+        if let Some(optimized) = self.optimized.remove(word) {
+            if let Some(DictEntry::Defined(block)) = self.dict.get(word) {
+                if *block == optimized.block {
+                    self.dict.insert(word.into(), DictEntry::Defined(optimized.original));
+                }
+            }
+        }
+    }
+
+    /// Discard the optimizations that depend on a word that is going to change.
+    fn invalidate(&mut self, word: &str) {
+        if self.optimized.is_empty() {
+            return;
+        }
+        // The optimization of the word itself is lost with the new definition
+        self.optimized.remove(word);
+        self.deoptimize_users(word);
+    }
+
+    /// Restore the original definition of the optimized words that inlined or folded `word`.
+    pub fn deoptimize_users(&mut self, word: &str) {
+        if self.optimized.is_empty() {
+            return;
+        }
+        let stale: Vec<String> = self.optimized.iter()
+            .filter(|(_, optimized)| optimized.deps.iter().any(|dep| dep == word))
+            .map(|(optimized_word, _)| optimized_word.clone())
+            .collect();
+        for stale_word in stale {
+            self.deoptimize(&stale_word);
+        }
+    }
+
+    /// Save the current entry of a word in the last marker, if it wasn't already saved.
+    fn save(&mut self, word: &str) {
+        if let Some(marker) = self.markers.last_mut() {
+            if !marker.saved.contains_key(word) {
+                marker.saved.insert(word.into(), self.dict.get(word).cloned());
+            }
+        }
+    }
+
     /// Define a native word
-    pub fn native(&mut self, word: &str, func: fn(&mut Pack) -> Result<bool, Error>) {
+    pub fn native(&mut self, word: &str, func: NativeFn) -> Result<(), Error> {
         let lex = self.lex.clone();
-        self.dict.insert(lex + word, DictEntry::Native(func));
+        self.insert(lex + word, DictEntry::Native(func))
+    }
+
+    /// Define an internal word, used in the blocks generated by natives. Internal words are executed directly,
+    /// without resolution, sandbox checks or hooks. Returns the name to use, prefixed with `INTERNAL`.
+    pub fn internal(&mut self, word: &str, func: NativeFn) -> String {
+        let name = format!("{}{}", INTERNAL, word);
+        self.internal.insert(name.clone(), func);
+        name
     }
 
     /// Define block word
-    pub fn block(&mut self, word: &str, block: BlockRef) {
+    pub fn block(&mut self, word: &str, block: BlockRef) -> Result<(), Error> {
         let lex = self.lex.clone();
-        self.dict.insert(lex + word, DictEntry::Defined(block));
+        self.insert(lex + word, DictEntry::Defined(block))
     }
 
     /// Define data word
-    pub fn data(&mut self, word: &str, cell: Cell) {
+    pub fn data(&mut self, word: &str, cell: Cell) -> Result<(), Error> {
         let lex = self.lex.clone();
-        self.dict.insert(lex + word, DictEntry::Data(cell));
+        self.insert(lex + word, DictEntry::Data(cell))
+    }
+
+    /// Set the lexicon prefix, saving the imports to restore them with `close_lex`.
+    pub fn open_lex(&mut self, lex: &str) {
+        self.lex = self.lex.clone() + lex + ".";
+        self.lex_imports.push(self.imports.len());
+    }
+
+    /// Remove the last lexicon from the prefix, and the imports made inside it.
+    pub fn close_lex(&mut self) {
+        let lex_len = self.lex[..self.lex.len().saturating_sub(1)].rfind('.').map(|i| i + 1).unwrap_or(0);
+        self.lex.truncate(lex_len);
+        if let Some(imports_len) = self.lex_imports.pop() {
+            self.imports.truncate(imports_len);
+        }
+    }
+
+    /// Number of lexicons currently open.
+    pub fn lex_depth(&self) -> usize {
+        self.lex_imports.len()
+    }
+
+    /// Restore a lexicon state: the prefix, the imports and the number of open lexicons, as given by `lex_depth`.
+    pub fn restore_lex(&mut self, lex: String, imports: Vec<String>, depth: usize) {
+        self.lex = lex;
+        self.imports = imports;
+        self.lex_imports.truncate(depth);
+    }
+
+    /// Import a lexicon (like `"math"`), so its words can be used without the prefix.
+    pub fn import(&mut self, lex: &str) {
+        let prefix = String::from(lex) + ".";
+        self.imports.retain(|imported| *imported != prefix);
+        self.imports.push(prefix);
+    }
+
+    /// Index of the current scope in `scopes`, `GLOBAL_SCOPE` if there is no lexicon and no imports.
+    pub fn scope_index(&mut self) -> usize {
+        if self.scopes.is_empty() {
+            self.scopes.resize(GLOBAL_SCOPE + 1, Scope::default());
+        }
+        if self.lex.is_empty() && self.imports.is_empty() {
+            return GLOBAL_SCOPE;
+        }
+        let last = self.scopes.len() - 1;
+        if last > GLOBAL_SCOPE && self.scopes[last].lex == self.lex && self.scopes[last].imports == self.imports {
+            last
+        }
+        else {
+            self.scopes.push(Scope { lex: self.lex.clone(), imports: self.imports.clone() });
+            last + 1
+        }
+    }
+
+    /// Seal a word, so it can't be redefined. A sealed word can't be shadowed either: inside a lexicon it always
+    /// resolves to itself, even if the lexicon defines a word with the same name.
+    pub fn seal(&mut self, word: &str) {
+        self.sealed.insert(word.into());
+    }
+
+    /// Seal a lexicon prefix (like `"math."`), so no word starting with it can be defined.
+    pub fn seal_lex(&mut self, prefix: &str) {
+        self.sealed_lex.push(prefix.into());
+    }
+
+    /// Seal all the words currently defined. Used after `Pack::new` it seals the primitives and the prelude.
+    pub fn seal_all(&mut self) {
+        let Self { dict, sealed, .. } = self;
+        sealed.extend(dict.keys().cloned());
+    }
+
+    /// Remove the seal of a word or lexicon prefix.
+    pub fn unseal(&mut self, word: &str) {
+        self.sealed.remove(word);
+        self.sealed_lex.retain(|prefix| prefix != word);
+    }
+
+    /// Check if a word is sealed.
+    pub fn is_sealed(&self, word: &str) -> bool {
+        self.sealed.contains(word) || self.sealed_lex.iter().any(|prefix| word.starts_with(prefix.as_str()))
+    }
+
+    /// Make a word private to its lexicon (like `"math.helper"`), so it can only be used from code in the same lexicon.
+    pub fn make_private(&mut self, word: &str) {
+        self.private.insert(word.into());
+    }
+
+    /// Check if a word is private.
+    pub fn is_private(&self, word: &str) -> bool {
+        self.private.contains(word)
+    }
+
+    /// Check if a word can be used from code in lexicon `lex`. Public words are always visible, private words only
+    /// from their own lexicon and the lexicons nested in it.
+    pub fn is_visible(&self, word: &str, lex: &str) -> bool {
+        if self.private.is_empty() || !self.private.contains(word) {
+            return true;
+        }
+        let word_lex = &word[..word.rfind('.').map(|i| i + 1).unwrap_or(0)];
+        lex.starts_with(word_lex)
+    }
+
+    /// All defined words, sorted by name.
+    pub fn words(&self) -> Vec<&String> {
+        self.lex_words("")
+    }
+
+    /// Words in a lexicon (all words starting with `prefix`), sorted by name.
+    pub fn lex_words(&self, prefix: &str) -> Vec<&String> {
+        let mut words: Vec<&String> = self.dict.keys().filter(|word| word.starts_with(prefix)).collect();
+        words.sort_unstable();
+        words
+    }
+
+    /// Kind of a word: "native", "defined" or "data".
+    pub fn kind(&self, word: &str) -> Option<&'static str> {
+        self.dict.get(word).map(|entry| entry.kind())
+    }
+
+    /// Block of a defined word.
+    pub fn body(&self, word: &str) -> Option<BlockRef> {
+        if let Some(DictEntry::Defined(block)) = self.dict.get(word) {
+            Some(*block)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Find the innermost defined word whose block contains the concat position `pos`
+    pub fn word_at(&self, pos: usize) -> Option<(&String, BlockRef)> {
+        let mut found: Option<(&String, BlockRef)> = None;
+        for (word, entry) in self.dict.iter() {
+            if let DictEntry::Defined(block) = entry {
+                if pos >= block.pos && pos < block.pos + block.len {
+                    match found {
+                        Some((inner_word, inner)) if inner.len < block.len || (inner.len == block.len && inner_word <= word) => {},
+                        _ => found = Some((word, *block)),
+                    }
+                }
+            }
+        }
+        found
     }
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+/// Local variables of a block invocation.
+pub struct Frame {
+    /// Size of the return stack when the frame was created. The frame is released when the block returns.
+    pub depth: usize,
+    /// Concat position where the locals are defined. They are visible from here to `end`.
+    pub start: usize,
+    /// Concat position of the `}` that closes the block.
+    pub end: usize,
+    pub names: Rc<Vec<String>>,
+    pub values: Vec<Cell>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, PartialOrd)]
 /// Return stack
 pub struct RetStack {
     stack: Vec<usize>,
+    frames: Vec<Frame>,
 }
 
 impl RetStack {
@@ -246,15 +779,83 @@ impl RetStack {
         self.stack.push(ret_pos)
     }
 
-    /// Pop value from return stack
+    /// Pop value from return stack. The frames of the blocks that returned are released.
     pub fn pop(&mut self) -> Option<usize> {
-        self.stack.pop()
+        let ret_pos = self.stack.pop();
+        while let Some(frame) = self.frames.last() {
+            if frame.depth <= self.stack.len() {
+                break;
+            }
+            self.frames.pop();
+        }
+        ret_pos
+    }
+
+    /// Replace the value at the top of the return stack, keeping the frames. Returns the old value.
+    pub fn replace_top(&mut self, ret_pos: usize) -> Option<usize> {
+        self.stack.last_mut().map(|top| core::mem::replace(top, ret_pos))
+    }
+
+    /// Push a frame of local variables
+    pub fn push_frame(&mut self, frame: Frame) {
+        self.frames.push(frame);
+    }
+
+    /// Check if there are frames of local variables
+    pub fn has_frames(&self) -> bool {
+        !self.frames.is_empty()
+    }
+
+    /// Get a local variable visible from concat position `pos`.
+    pub fn local(&self, name: &str, pos: usize) -> Option<&Cell> {
+        let (frame, index) = self.find_local(name, pos)?;
+        self.frames[frame].values.get(index)
+    }
+
+    /// Get a mutable local variable visible from concat position `pos`.
+    pub fn local_mut(&mut self, name: &str, pos: usize) -> Option<&mut Cell> {
+        let (frame, index) = self.find_local(name, pos)?;
+        self.frames[frame].values.get_mut(index)
+    }
+
+    /// All local variables visible from concat position `pos`, from the outermost frame to the innermost.
+    pub fn visible_locals(&self, pos: usize) -> (Vec<String>, Vec<Cell>) {
+        self.frames.iter()
+            .filter(|frame| frame.start <= pos && pos < frame.end)
+            .flat_map(|frame| frame.names.iter().cloned().zip(frame.values.iter().cloned()))
+            .unzip()
+    }
+
+    /// Find a local variable, searching from the innermost frame. Returns the frame index and the variable index.
+    fn find_local(&self, name: &str, pos: usize) -> Option<(usize, usize)> {
+        self.frames.iter().enumerate().rev()
+            .filter(|(_, frame)| frame.start <= pos && pos < frame.end)
+            .find_map(|(i, frame)| frame.names.iter().rposition(|n| n == name).map(|index| (i, index)))
     }
 
     /// Stack size
     pub fn size(&self) -> usize {
         self.stack.len()
     }
+
+    /// Get return address. Position is referenced to the top.
+    pub fn get(&self, pos: usize) -> Option<usize> {
+        if pos < self.size() {
+            self.stack.get(self.stack.len() - 1 - pos).copied()
+        }
+        else {
+            None
+        }
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+/// Location of a cell in the source code
+pub struct Location {
+    /// Index of the source name in the Concat.
+    pub source: usize,
+    /// Line number, starting at 1.
+    pub line: usize,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -262,10 +863,70 @@ impl RetStack {
 pub struct Concat {
     pub array: Vec<Cell>,
     pub pointer: usize,
+    /// Names of the sources appended to the Concat.
+    pub sources: Vec<String>,
+    /// Source location of each cell, if any.
+    pub locations: Vec<Option<Location>>,
+    /// Scope of each cell, as an index of `Dictionary::scopes`. `UNSCOPED` if the cell runs in the current scope.
+    pub scopes: Vec<usize>,
+    /// Stack transfers already compiled, by concat position.
+    pub transfers: HashMap<usize, Transfer>,
+    /// Local variable definitions already compiled, by concat position.
+    pub locals: HashMap<usize, Locals>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// A compiled definition of local variables, like `{ | a b | ... }`.
+pub struct Locals {
+    pub names: Rc<Vec<String>>,
+    /// Number of cells of the definition in the concat, after the first `|`.
+    pub len: usize,
+    /// Concat position of the `}` that closes the block.
+    pub end: usize,
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// A compiled stack transfer, like `[ a b | b a ]`.
+pub struct Transfer {
+    /// Number of cells taken from the stack.
+    pub inputs: usize,
+    /// Cells put in the stack, as positions referenced to the top before the transfer.
+    pub outputs: Vec<usize>,
+    /// Number of cells of the transfer in the concat, after the `[`.
+    pub len: usize,
 }
 
 impl Concat {
+    /// Get source location of the cell at position `pos`
+    pub fn location(&self, pos: usize) -> Option<Location> {
+        self.locations.get(pos).copied().flatten()
+    }
+
+    /// Set the scope of the cells in a block that don't have one yet
+    pub fn set_scope(&mut self, block: BlockRef, scope: usize) {
+        let end = block.pos + block.len;
+        if self.scopes.len() < end {
+            self.scopes.resize(end, UNSCOPED);
+        }
+        for cell_scope in self.scopes[block.pos..end].iter_mut() {
+            if *cell_scope == UNSCOPED {
+                *cell_scope = scope;
+            }
+        }
+    }
+
+    /// Get source name of a location
+    pub fn source(&self, location: Location) -> Option<&str> {
+        self.sources.get(location.source).map(|s| s.as_str())
+    }
+
+    /// Get the cells of a block
+    pub fn block(&self, block: BlockRef) -> Option<&[Cell]> {
+        self.array.get(block.pos..block.pos + block.len)
+    }
+
     /// Get next cell from the Concat
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<&Cell> {
         if self.pointer < self.array.len() {
             let cell = &self.array[self.pointer];
@@ -290,12 +951,14 @@ impl Concat {
     }
 }
 
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, PartialOrd)]
 /// Stack structure
 pub struct Stack {
     stack: Vec<Cell>,
     base: usize,
     nested: Vec<usize>,
+    /// Nesting levels of the stacks that become a list when closed.
+    lists: Vec<usize>,
 }
 
 impl Stack {
@@ -305,8 +968,27 @@ impl Stack {
         self.base = self.stack.len();
     }
 
+    /// Starts a new nested stack that becomes a list when it ends
+    pub fn start_list(&mut self) {
+        self.start_stack();
+        self.lists.push(self.nested.len());
+    }
+
+    /// Number of nested stacks open
+    pub fn level(&self) -> usize {
+        self.nested.len()
+    }
+
+    /// Current stack becomes a list when it ends
+    pub fn is_list(&self) -> bool {
+        self.lists.last() == Some(&self.nested.len()) && !self.nested.is_empty()
+    }
+
     /// Ends current stack
     pub fn end_stack(&mut self) -> Option<usize> {
+        if self.is_list() {
+            self.lists.pop();
+        }
         if let Some(base) = self.nested.pop() {
             self.base = base;
             Some(base)
@@ -352,6 +1034,32 @@ impl Stack {
         }
     }
 
+    /// Remove cell. Position is referenced to the top.
+    pub fn remove(&mut self, pos: usize) -> Option<Cell> {
+        if pos < self.size() {
+            let len = self.stack.len();
+            Some(self.stack.remove(len - 1 - pos))
+        }
+        else {
+            None
+        }
+    }
+
+    /// Replace the `inputs` cells at the top with copies of them, in the order given by `outputs`.
+    /// Output positions are referenced to the top, before the transfer. Returns false if there aren't enough cells.
+    pub fn transfer(&mut self, inputs: usize, outputs: &[usize]) -> bool {
+        if inputs > self.size() || outputs.iter().any(|pos| *pos >= inputs) {
+            return false;
+        }
+        let top = self.stack.len() - 1;
+        for pos in outputs {
+            let cell = self.stack[top - pos].clone();
+            self.stack.push(cell);
+        }
+        self.stack.drain(top + 1 - inputs..top + 1);
+        true
+    }
+
     /// Size of current stack
     pub fn size(&self) -> usize {
         self.stack.len() - self.base
@@ -361,6 +1069,77 @@ impl Stack {
     pub fn clear(&mut self) {
         self.stack.drain(self.base..);
     }
+
+    /// Take all cells from current stack, from the bottom to the top
+    pub fn take(&mut self) -> Vec<Cell> {
+        self.stack.split_off(self.base)
+    }
+}
+
+#[derive(Default, Debug, Clone)]
+/// Policy restricting the words a script can execute.
+///
+/// Patterns are word names, or lexicon prefixes when they end with a dot (like `"io."`).
+/// Words reached indirectly, with `exe`, `if`, `while`/`do` or inside other words, are also checked.
+pub struct Sandbox {
+    allowed: Option<Vec<String>>,
+    denied: Vec<String>,
+}
+
+impl Sandbox {
+    /// Allow a word or lexicon. Once something is allowed, any word not allowed is forbidden.
+    pub fn allow(&mut self, pattern: &str) {
+        self.allowed.get_or_insert_with(Vec::new).push(pattern.into());
+    }
+
+    /// Forbid a word or lexicon. Denials take precedence over allowances.
+    pub fn deny(&mut self, pattern: &str) {
+        self.denied.push(pattern.into());
+    }
+
+    /// Check if the sandbox forbids any word.
+    pub fn is_restricted(&self) -> bool {
+        self.allowed.is_some() || !self.denied.is_empty()
+    }
+
+    /// Check if a word can be executed.
+    pub fn permits(&self, word: &str) -> bool {
+        let matches = |pattern: &String| {
+            if pattern.ends_with('.') {
+                word.starts_with(pattern.as_str())
+            }
+            else {
+                word == pattern
+            }
+        };
+        if self.denied.iter().any(matches) {
+            false
+        }
+        else if let Some(allowed) = &self.allowed {
+            allowed.iter().any(matches)
+        }
+        else {
+            true
+        }
+    }
+}
+
+/// Observer of the interpreter execution. All methods do nothing by default.
+pub trait Hook {
+    /// Called before executing a cell from the Concat.
+    fn on_step(&mut self, _pack: &Pack) {}
+    /// Called before executing a word.
+    fn before_word(&mut self, _pack: &Pack, _word: &str) {}
+    /// Called after executing a word successfully. Defined words are "executed" when the interpreter jumps into the block.
+    fn after_word(&mut self, _pack: &Pack, _word: &str) {}
+    /// Called after a word is defined with `@def`.
+    fn on_define(&mut self, _pack: &Pack, _word: &str) {}
+    /// Called after opening a nested stack.
+    fn on_stack_open(&mut self, _pack: &Pack) {}
+    /// Called after closing a nested stack.
+    fn on_stack_close(&mut self, _pack: &Pack) {}
+    /// Called when a step fails.
+    fn on_error(&mut self, _pack: &Pack, _error: &Error) {}
 }
 
 #[derive(Default, Clone)]
@@ -370,18 +1149,47 @@ pub struct Pack {
     pub dictionary: Dictionary,
     pub ret: RetStack,
     pub concat: Concat,
+    pub hooks: Vec<Rc<RefCell<dyn Hook>>>,
+    pub sandbox: Sandbox,
+    pub modules: Modules,
+    pub docs: Docs,
+    pub checks: Checks,
+    /// Retain stack, where the combinators keep cells while the code they call runs.
+    pub retain: Stack,
+    pub trampolines: Trampolines,
 }
 
 impl Pack {
     /// Create a new Pack with registered primitives and prelude.
+    ///
+    /// The prelude is executed, so the dictionary is complete once the Pack is created and the Concat pointer is
+    /// at the end of it.
+    ///
+    /// # Panics
+    ///
+    /// If the primitives can't be registered or the prelude fails, what means a bug in RunPack.
     pub fn new() -> Self {
         let mut pack = Pack::default();
-        register_primitives(&mut pack);
-        pack.code(PRELUDE);
+        register_primitives(&mut pack).expect("Failed registering the primitives");
+        // No checks are enabled yet, so there are no diagnostics
+        let _ = pack.code_named("prelude", PRELUDE);
+        pack.run().expect("Failed running the prelude");
         pack
     }
 
-    fn next_cell(&mut self, code: &str, mut pos: usize) -> (Option<Cell>, usize) {
+    /// Split code into cells, without appending them to the Concat.
+    pub(crate) fn tokenize(&self, code: &str) -> Vec<Cell> {
+        let mut cells = Vec::new();
+        let mut pos = 0;
+        while let (Some(cell), _, next_pos) = self.next_cell(code, pos) {
+            cells.push(cell);
+            pos = next_pos;
+        }
+        cells
+    }
+
+    fn next_cell(&self, code: &str, mut pos: usize) -> (Option<Cell>, usize, usize) {
+        let mut start = pos;
         let mut word_found = false;
         let mut in_string = false;
         let mut in_comment = false;
@@ -423,6 +1231,7 @@ impl Pack {
                 if b == 39 {    // apostrophe
                     in_string = true;
                     word_found = true;
+                    start = pos - 1;
                 }
                 else if b == 34 {    // quotation mark
                     in_comment = true;
@@ -433,6 +1242,9 @@ impl Pack {
                     }
                 }
                 else {
+                    if !word_found {
+                        start = pos - 1;
+                    }
                     word_found = true;
                     buff.push(b);
                 }
@@ -441,24 +1253,24 @@ impl Pack {
 
         if in_string {
             if let Ok(token) = String::from_utf8(buff) {
-                (Some(token.into()), pos)
+                (Some(token.into()), start, pos)
             }
             else {
                 //TODO: string parse error
-                (None, pos)
+                (None, start, pos)
             }
         }
         else {
-            if buff.len() > 0 {
-                (self.parse_token(buff), pos)
+            if !buff.is_empty() {
+                (self.parse_token(buff), start, pos)
             }
             else {
-                (None, pos)
+                (None, start, pos)
             }
         }
     }
 
-    fn parse_token(&mut self, token: Vec<u8>) -> Option<Cell> {
+    fn parse_token(&self, token: Vec<u8>) -> Option<Cell> {
         if let Ok(token) = String::from_utf8(token) {
             if let Some(num_cell) = Cell::number(&token) {
                 Some(num_cell)
@@ -477,27 +1289,138 @@ impl Pack {
     }
 
     /// Define a batch of native functions
-    pub(crate) fn def_natives(&mut self, list: &[(&str, fn(&mut Pack) -> Result<bool, Error>)]) {
-        list.iter().for_each(|(word_name, function)| {
-            self.dictionary.native(word_name, *function);
-        });
+    pub(crate) fn def_natives(&mut self, list: &[(&str, NativeFn)]) -> Result<(), Error> {
+        list.iter().try_for_each(|(word_name, function)| {
+            self.dictionary.native(word_name, *function)
+        })
     }
     
-    /// Execute a word from the dictionary.
+    /// Find the full name of a word used in the cell at concat position `pos`.
+    ///
+    /// The search order is: the lexicon of the cell's scope and its parents, the imported lexicons from the
+    /// last to the first, and finally the word itself. Cells without scope use the current lex and imports.
+    /// Sealed words are not shadowed by the lexicons, they always resolve to themselves.
+    pub fn resolve<'a>(&self, word: &'a str, pos: usize) -> Option<Cow<'a, str>> {
+        if self.dictionary.is_sealed(word) && self.dictionary.dict.contains_key(word) {
+            return Some(word.into());
+        }
+        let (lex, imports) = self.scope(pos);
+        let found = |word: &str| self.dictionary.dict.contains_key(word) && self.dictionary.is_visible(word, lex);
+        if !lex.is_empty() || !imports.is_empty() {
+            // The prefixed names are built in a reused buffer, only the one found is allocated
+            let mut full_word = self.dictionary.resolve_buffer.borrow_mut();
+            let mut found_prefixed = |prefix: &str| {
+                full_word.clear();
+                full_word.push_str(prefix);
+                full_word.push_str(word);
+                found(&full_word)
+            };
+            let mut prefix = lex;
+            while !prefix.is_empty() {
+                if found_prefixed(prefix) {
+                    return Some(full_word.clone().into());
+                }
+                prefix = &prefix[..prefix[..prefix.len() - 1].rfind('.').map(|i| i + 1).unwrap_or(0)];
+            }
+            for prefix in imports.iter().rev() {
+                if found_prefixed(prefix) {
+                    return Some(full_word.clone().into());
+                }
+            }
+        }
+        if found(word) {
+            Some(word.into())
+        }
+        else {
+            None
+        }
+    }
+
+    /// Lexicon and imports in effect for the cell at concat position `pos`.
+    pub fn scope(&self, pos: usize) -> (&str, &[String]) {
+        match self.concat.scopes.get(pos).filter(|index| **index != UNSCOPED) {
+            Some(index) => (&self.dictionary.scopes[*index].lex, &self.dictionary.scopes[*index].imports),
+            None => (&self.dictionary.lex, &self.dictionary.imports),
+        }
+    }
+
+    /// Execute a word from the dictionary. The name is resolved in the scope of the current cell.
     /// 
     /// Note: If word is Defined, we must call `run` afterward.
     pub fn exec(&mut self, word: &str) -> Result<bool, Error> {
+        if word.starts_with(INTERNAL) {
+            if let Some(func) = self.dictionary.internal.get(word) {
+                return func(self);
+            }
+        }
+        // Local variables shadow the dictionary
+        if self.ret.has_frames() {
+            let pos = self.concat.pointer.wrapping_sub(1);
+            if let Some(cell) = self.ret.local(word, pos) {
+                let cell = cell.clone();
+                self.stack.push(cell);
+                return Ok(true);
+            }
+            if let Some(name) = word.strip_suffix('!') {
+                if self.ret.local(name, pos).is_some() {
+                    let value = self.stack.pop().ok_or_else(|| Error::new(format!("{}: stack is empty", word)))?;
+                    if let Some(local) = self.ret.local_mut(name, pos) {
+                        *local = value;
+                    }
+                    return Ok(true);
+                }
+            }
+        }
+        let word = match self.resolve(word, self.concat.pointer.wrapping_sub(1)) {
+            Some(word) => word,
+            None if self.dictionary.is_private(word) => {
+                return Err(Error::new(format!("Word '{}' is private, it can only be used inside its lexicon", word)));
+            },
+            None => return Err(Error::new(format!("Word '{}' doesn't exist in dictionary", word))),
+        };
+        let word = &*word;
+        if !self.sandbox.permits(word) {
+            return Err(Error::new(format!("Word '{}' is not allowed by the sandbox", word)));
+        }
         if let Some(dict_entry) = self.dictionary.dict.get(word) {
             // Cloning the DictEntry is necessary because a Data entry will have to be put into the stack,
             // and in the other two variants, Native and Defined, a clone has very little performance impact.
-            let dict_entry = dict_entry.clone();
-            self.exec_dict_entry(dict_entry)
+            let dict_entry = match self.dictionary.optimized.get(word) {
+                // The inlined words must go through the sandbox and the hooks, so the original definition is used
+                Some(optimized) if !self.hooks.is_empty() || self.sandbox.is_restricted() => {
+                    DictEntry::Defined(optimized.original)
+                },
+                _ => dict_entry.clone(),
+            };
+            if self.hooks.is_empty() {
+                self.exec_dict_entry(dict_entry)
+            }
+            else {
+                self.call_hooks(|hook, pack| hook.before_word(pack, word));
+                let result = self.exec_dict_entry(dict_entry);
+                if result.is_ok() {
+                    self.call_hooks(|hook, pack| hook.after_word(pack, word));
+                }
+                result
+            }
         }
         else {
             Err(Error::new(format!("Word '{}' doesn't exist in dictionary", word)))
         }
     }
 
+    /// Register an execution hook.
+    pub fn add_hook(&mut self, hook: Rc<RefCell<dyn Hook>>) {
+        self.hooks.push(hook);
+    }
+
+    /// Call all registered hooks.
+    pub fn call_hooks(&self, f: impl Fn(&mut dyn Hook, &Pack)) {
+        for hook in self.hooks.iter() {
+            f(&mut *hook.borrow_mut(), self);
+        }
+    }
+
     /// Execute a dictionary entry
     pub fn exec_dict_entry(&mut self, dict_entry: DictEntry) -> Result<bool, Error> {
         match dict_entry {
@@ -516,21 +1439,94 @@ impl Pack {
         }
     }
 
-    /// Append code to the end of the Concat.
-    pub fn code(&mut self, code: &str) {
+    /// Append code to the end of the Concat. Returns the problems found by the enabled `checks`.
+    #[must_use = "the diagnostics of the enabled checks would be lost"]
+    pub fn code(&mut self, code: &str) -> Vec<Diagnostic> {
+        self.code_named("", code)
+    }
+
+    /// Append code to the end of the Concat, recording `name` as the source of its cells.
+    /// Returns the problems found by the enabled `checks`.
+    #[must_use = "the diagnostics of the enabled checks would be lost"]
+    pub fn code_named(&mut self, name: &str, code: &str) -> Vec<Diagnostic> {
+        let start = self.concat.array.len();
+        let source = self.concat.sources.len();
+        self.concat.sources.push(name.into());
+        // cells appended without source (like those created by "block") have no location
+        self.concat.locations.resize(self.concat.array.len(), None);
         let mut pos = 0;
+        let mut line = 1;
+        let mut line_pos = 0;
         // tokenize and push cells into the Concat
         loop {
-            let (cell, tmp_pos) = self.next_cell(code, pos);
+            let (cell, start, tmp_pos) = self.next_cell(code, pos);
             pos = tmp_pos;
             if let Some(cell) = cell {
+                line += code.as_bytes()[line_pos..start].iter().filter(|b| **b == 10).count();
+                line_pos = start;
                 self.concat.array.push(cell);
+                self.concat.locations.push(Some(Location { source, line }));
             }
             else {
                 //TODO: add error handling, when the parser fails somewhere
                 break;
             }
         }
+        self.scope_cells(start);
+        let mut diagnostics = Vec::new();
+        if self.checks.lint {
+            diagnostics.extend(lint(self, start));
+        }
+        if self.checks.effects {
+            diagnostics.extend(check_effects(self, start));
+        }
+        diagnostics
+    }
+
+    /// Assign a scope to the cells parsed from position `start`, following the `lex`, `\lex` and `use` of the
+    /// code from the current lexicon and imports.
+    fn scope_cells(&mut self, start: usize) {
+        let dictionary = &mut self.dictionary;
+        let saved = (dictionary.lex.clone(), dictionary.imports.clone(), dictionary.lex_imports.clone());
+        let array = &self.concat.array;
+        let scopes = &mut self.concat.scopes;
+        scopes.resize(array.len(), UNSCOPED);
+        let mut pos = start;
+        while pos < array.len() {
+            scopes[pos] = dictionary.scope_index();
+            match (&array[pos], array.get(pos + 1)) {
+                (Cell::Word(w), Some(Cell::Word(name))) if w == "lex" || w == "use" => {
+                    scopes[pos + 1] = scopes[pos];
+                    if w == "lex" {
+                        dictionary.open_lex(name);
+                    }
+                    else {
+                        dictionary.import(name);
+                    }
+                    pos += 1;
+                },
+                (Cell::Word(w), _) if w == "\\lex" => dictionary.close_lex(),
+                _ => {},
+            }
+            pos += 1;
+        }
+        (dictionary.lex, dictionary.imports, dictionary.lex_imports) = saved;
+    }
+
+    /// Append a block to the end of the Concat, preceded by a skip so it's not executed when reached.
+    /// The cells must include the closing `}`, and they get the current scope.
+    pub fn append_block(&mut self, cells: Vec<Cell>) -> BlockRef {
+        let len = cells.len();
+        // New block will start at the end of current concat + 3 ("N skip {").
+        self.concat.array.push((len as i64 + 1).into());
+        self.concat.array.push(Cell::Word("skip".into()));
+        self.concat.array.push(Cell::Word("{".into()));
+        let pos = self.concat.array.len();
+        self.concat.array.extend(cells);
+        let block = BlockRef { pos, len };
+        let scope = self.dictionary.scope_index();
+        self.concat.set_scope(block, scope);
+        block
     }
 
     /// Run the script
@@ -549,10 +1545,20 @@ impl Pack {
 
     /// Run one cell from the Concat
     pub fn one_step(&mut self) -> Result<bool, Error> {
+        if !self.hooks.is_empty() && self.concat.pointer < self.concat.array.len() {
+            self.call_hooks(|hook, pack| hook.on_step(pack));
+        }
         if let Some(cell) = self.concat.next() {
             let cell = cell.clone();
             match cell {
-                Cell::Word(w) => return self.exec(&w),
+                Cell::Word(w) => {
+                    let result = self.exec(&w);
+                    if let Err(e) = &result {
+                        self.abort();
+                        self.call_hooks(|hook, pack| hook.on_error(pack, e));
+                    }
+                    return result;
+                },
                 _ => self.stack.push(cell),
             }
             Ok(true)
@@ -562,10 +1568,118 @@ impl Pack {
         }
     }
 
+    /// Undo the state of an execution aborted by an error. The modules being loaded restore the lexicon state of the
+    /// code that required them, and are not marked as loaded, so they can be required again. The cells kept in the
+    /// retain stack by the combinators in progress are discarded.
+    fn abort(&mut self) {
+        while let Some(loading) = self.modules.loading.pop() {
+            self.dictionary.restore_lex(loading.lex, loading.imports, loading.depth);
+        }
+        self.retain = Stack::default();
+    }
+
+    /// Capture the current execution state as a continuation.
+    pub fn capture(&self) -> Continuation {
+        Continuation { pointer: self.concat.pointer, ret: self.ret.clone(), retain: Rc::new(self.retain.clone()) }
+    }
+
+    /// Resume execution at a continuation, replacing the current return and retain stacks.
+    pub fn resume(&mut self, cont: Continuation) {
+        self.concat.pointer = cont.pointer;
+        self.ret = cont.ret;
+        self.retain = Rc::unwrap_or_clone(cont.retain);
+    }
+
+    /// Enter a closure: jump into its block with a frame holding the captured locals.
+    /// 
+    /// Note: We must call `run` afterward.
+    pub fn enter(&mut self, closure: &Closure) {
+        self.ret.push(self.concat.pointer);
+        let BlockRef { pos, len } = closure.block;
+        self.ret.push_frame(Frame {
+            depth: self.ret.size(),
+            start: pos,
+            end: pos + len - 1,
+            names: closure.names.clone(),
+            values: closure.values.clone(),
+        });
+        self.concat.pointer = pos + closure.skip;
+    }
+
+    /// Jump into a block, closure, quotation or word, or resume a continuation. Other cells are pushed into the stack.
+    /// 
+    /// Note: We must call `run` afterward.
+    pub fn jump(&mut self, cell: Cell) -> Result<bool, Error> {
+        match cell {
+            Cell::Block(block) => {
+                self.ret.push(self.concat.pointer);
+                self.concat.pointer = block.pos;
+                Ok(true)
+            },
+            Cell::Word(word) => self.exec(&word),
+            Cell::Closure(closure) => {
+                self.enter(&closure);
+                Ok(true)
+            },
+            Cell::Quotation(quot) => {
+                for arg in quot.args {
+                    self.stack.push(arg);
+                }
+                let mut calls = quot.calls.into_iter();
+                match calls.next() {
+                    Some(first) if calls.len() > 0 => {
+                        // The rest of the calls is executed when the first returns
+                        self.retain.push(Quotation { args: Vec::new(), calls: calls.collect() }.into());
+                        self.jump_then(first, self.trampolines.call)
+                    },
+                    Some(first) => self.jump(first),
+                    None => Ok(true),
+                }
+            },
+            Cell::Continuation(cont) => {
+                self.resume(cont);
+                Ok(true)
+            },
+            cell => {
+                self.stack.push(cell);
+                Ok(true)
+            },
+        }
+    }
+
+    /// Jump into a block, closure, quotation or word, and when it returns, continue with the `then` block.
+    /// 
+    /// Note: We must call `run` afterward.
+    pub fn jump_then(&mut self, cell: Cell, then: BlockRef) -> Result<bool, Error> {
+        self.ret.push(self.concat.pointer);
+        self.concat.pointer = then.pos;
+        self.jump(cell)
+    }
+
+    /// Execute a block, closure, quotation or word, and run until it returns. Other cells are pushed into the stack,
+    /// like `exe` does.
+    /// 
+    /// It's used by natives that execute code and need the result before continuing. Returns false if the code escaped
+    /// instead of returning, with `leave` or a continuation. Then the native must stop and return, so the execution
+    /// continues where the escape went.
+    pub fn call(&mut self, cell: Cell) -> Result<bool, Error> {
+        let depth = self.ret.size();
+        if let Cell::Continuation(_) = cell {
+            return Err(Error::new("call: Continuations can't be called".into()));
+        }
+        self.jump(cell)?;
+        while self.ret.size() > depth {
+            if !self.one_step()? {
+                return Err(Error::new("call: Reached the end of the concat before returning".into()));
+            }
+        }
+        Ok(self.ret.size() == depth)
+    }
+
     //TODO: create an async version of "run_word" to partner with "async_run".
 
     /// Async version of run().
-    pub fn async_run(&mut self) -> RunFuture {
+    pub fn async_run(&mut self) -> RunFuture<'_> {
         RunFuture::new(self)
     }
 }
@@ -0,0 +1,184 @@
+use super::core::{Pack, Hook, Cell, DictEntry};
+use hashbrown::HashMap;
+use alloc::{vec::Vec, string::String, format};
+use core::fmt::Write;
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+/// Branch coverage of a conditional word (`if`, `either` or `do`).
+pub struct Branch {
+    /// Concat position of the conditional word.
+    pub pos: usize,
+    /// Times the true branch was taken.
+    pub taken_true: u64,
+    /// Times the false branch was taken.
+    pub taken_false: u64,
+}
+
+#[derive(Default, Debug, Clone)]
+/// Code coverage tracker. It's a Hook, so it starts tracking once registered with `Pack::add_hook`.
+pub struct Coverage {
+    hits: HashMap<usize, u64>,
+    branches: HashMap<usize, Branch>,
+    calls: HashMap<String, u64>,
+}
+
+impl Coverage {
+    /// Create an empty coverage tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clear all counters.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Times the cell at a concat position was executed.
+    pub fn hits(&self, pos: usize) -> u64 {
+        self.hits.get(&pos).copied().unwrap_or(0)
+    }
+
+    /// Concat positions and execution counts of the cells in the block of a defined word.
+    pub fn word(&self, pack: &Pack, word: &str) -> Option<Vec<(usize, u64)>> {
+        if let Some(DictEntry::Defined(block)) = pack.dictionary.dict.get(word) {
+            Some((block.pos..block.pos + block.len).map(|pos| (pos, self.hits(pos))).collect())
+        }
+        else {
+            None
+        }
+    }
+
+    /// Branches found in the block of a defined word.
+    pub fn word_branches(&self, pack: &Pack, word: &str) -> Option<Vec<Branch>> {
+        if let Some(DictEntry::Defined(block)) = pack.dictionary.dict.get(word) {
+            Some((block.pos..block.pos + block.len).filter_map(|pos| self.branch(pack, pos)).collect())
+        }
+        else {
+            None
+        }
+    }
+
+    /// Coverage of the defined words in LCOV format, grouped by source. Code from the prelude is excluded.
+    pub fn lcov(&self, pack: &Pack) -> String {
+        // Collect defined words and the cells of their blocks, sorted by concat position
+        let mut words: Vec<(&String, usize, usize)> = pack.dictionary.dict.iter().filter_map(|(word, entry)| {
+            if let DictEntry::Defined(block) = entry {
+                Some((word, block.pos, block.len))
+            }
+            else {
+                None
+            }
+        }).collect();
+        words.sort_by(|a, b| (a.1, a.0).cmp(&(b.1, b.0)));
+        let mut cells: Vec<usize> = words.iter().flat_map(|(_, pos, len)| *pos..*pos + *len).collect();
+        cells.sort_unstable();
+        cells.dedup();
+
+        let mut out = String::new();
+        for (source, name) in pack.concat.sources.iter().enumerate() {
+            if name == "prelude" {
+                continue;
+            }
+            let in_source = |pos: usize| pack.concat.location(pos).filter(|loc| loc.source == source).map(|loc| loc.line);
+            let source_cells: Vec<(usize, usize)> = cells.iter().filter_map(|pos| in_source(*pos).map(|line| (*pos, line))).collect();
+            if source_cells.is_empty() {
+                continue;
+            }
+            let file = if name.is_empty() { format!("source{}", source) } else { name.clone() };
+            let _ = writeln!(out, "TN:\nSF:{}", file);
+            // Functions
+            let (mut fnf, mut fnh) = (0, 0);
+            for (word, pos, _) in words.iter() {
+                if let Some(line) = in_source(*pos) {
+                    let calls = self.calls.get(*word).copied().unwrap_or(0);
+                    let _ = writeln!(out, "FN:{},{}", line, word);
+                    let _ = writeln!(out, "FNDA:{},{}", calls, word);
+                    fnf += 1;
+                    if calls > 0 {
+                        fnh += 1;
+                    }
+                }
+            }
+            let _ = writeln!(out, "FNF:{}\nFNH:{}", fnf, fnh);
+            // Branches
+            let (mut brf, mut brh) = (0, 0);
+            for (n, (pos, line)) in source_cells.iter().enumerate() {
+                if let Some(branch) = self.branch(pack, *pos) {
+                    let executed = self.hits(*pos) > 0;
+                    for (i, taken) in [branch.taken_true, branch.taken_false].iter().enumerate() {
+                        if executed {
+                            let _ = writeln!(out, "BRDA:{},{},{},{}", line, n, i, taken);
+                        }
+                        else {
+                            let _ = writeln!(out, "BRDA:{},{},{},-", line, n, i);
+                        }
+                        brf += 1;
+                        if *taken > 0 {
+                            brh += 1;
+                        }
+                    }
+                }
+            }
+            let _ = writeln!(out, "BRF:{}\nBRH:{}", brf, brh);
+            // Lines, with the count of the most executed cell in the line
+            let mut lines: Vec<(usize, u64)> = Vec::new();
+            for (pos, line) in source_cells.iter() {
+                match lines.iter_mut().find(|(l, _)| l == line) {
+                    Some((_, count)) => *count = (*count).max(self.hits(*pos)),
+                    None => lines.push((*line, self.hits(*pos))),
+                }
+            }
+            lines.sort_unstable();
+            for (line, count) in lines.iter() {
+                let _ = writeln!(out, "DA:{},{}", line, count);
+            }
+            let lh = lines.iter().filter(|(_, count)| *count > 0).count();
+            let _ = writeln!(out, "LF:{}\nLH:{}\nend_of_record", lines.len(), lh);
+        }
+        out
+    }
+
+    fn branch(&self, pack: &Pack, pos: usize) -> Option<Branch> {
+        match pack.concat.array.get(pos) {
+            Some(Cell::Word(w)) if w == "if" || w == "either" || w == "do" => {
+                Some(self.branches.get(&pos).copied().unwrap_or(Branch { pos, ..Branch::default() }))
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Hook for Coverage {
+    fn before_word(&mut self, pack: &Pack, word: &str) {
+        if let Some(DictEntry::Defined(_)) = pack.dictionary.dict.get(word) {
+            *self.calls.entry(word.into()).or_default() += 1;
+        }
+    }
+
+    fn on_step(&mut self, pack: &Pack) {
+        let pos = pack.concat.pointer;
+        *self.hits.entry(pos).or_default() += 1;
+        // Conditional words take the condition from the stack
+        let condition = match pack.concat.array.get(pos) {
+            Some(Cell::Word(w)) if w == "if" || w == "do" => pack.stack.get(0),
+            Some(Cell::Word(w)) if w == "either" => pack.stack.get(2),
+            _ => return,
+        };
+        if let Some(Cell::Boolean(condition)) = condition {
+            let branch = self.branches.entry(pos).or_insert(Branch { pos, ..Branch::default() });
+            if *condition {
+                branch.taken_true += 1;
+            }
+            else {
+                branch.taken_false += 1;
+            }
+            // The branch words of "if" are consumed, not stepped
+            if let Some(Cell::Word(w)) = pack.concat.array.get(pos) {
+                if w == "if" {
+                    let taken = if *condition { pos + 1 } else { pos + 2 };
+                    *self.hits.entry(taken).or_default() += 1;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use super::core::{Pack, Cell, BlockRef, DictEntry, Location, Error};
+use alloc::{vec::Vec, string::String};
+
+#[derive(PartialEq, Clone, Debug)]
+/// Reason why the debugger stopped the execution.
+pub enum Pause {
+    /// The requested step finished.
+    Step,
+    /// Reached a breakpoint, at the given concat position.
+    Breakpoint(usize),
+    /// A watched word changed its value.
+    Watch {
+        word: String,
+        old: Option<Cell>,
+        new: Option<Cell>,
+    },
+    /// Reached the end of the Concat.
+    End,
+}
+
+#[derive(Default, Debug, Clone)]
+/// Step debugger, drives the execution of a Pack one cell at a time.
+pub struct Debugger {
+    words: Vec<String>,
+    positions: Vec<usize>,
+    locations: Vec<Location>,
+    watches: Vec<(String, Option<Cell>)>,
+    /// Concat position where the execution is paused, its breakpoint is not hit again when resuming.
+    paused: Option<usize>,
+}
+
+impl Debugger {
+    /// Create a debugger without breakpoints or watches.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Break before executing a word. Defined words break when entering their block, so calls with `exe` are also caught.
+    pub fn break_word(&mut self, word: &str) {
+        self.words.push(word.into());
+    }
+
+    /// Break before executing the cell at a concat position.
+    pub fn break_at(&mut self, pos: usize) {
+        self.positions.push(pos);
+    }
+
+    /// Break before executing any cell in a source location.
+    pub fn break_location(&mut self, location: Location) {
+        self.locations.push(location);
+    }
+
+    /// Remove all breakpoints.
+    pub fn clear_breakpoints(&mut self) {
+        self.words.clear();
+        self.positions.clear();
+        self.locations.clear();
+    }
+
+    /// Pause when the value of a data word (like a `var`) changes.
+    pub fn watch(&mut self, pack: &Pack, word: &str) {
+        self.watches.push((word.into(), Self::value(pack, word)));
+    }
+
+    /// Remove a watch.
+    pub fn unwatch(&mut self, word: &str) {
+        self.watches.retain(|(w, _)| w != word);
+    }
+
+    /// Run one cell.
+    pub fn step_into(&mut self, pack: &mut Pack) -> Result<Pause, Error> {
+        self.run_until(pack, |_| true)
+    }
+
+    /// Run one cell, and if it's a call, run until it returns.
+    pub fn step_over(&mut self, pack: &mut Pack) -> Result<Pause, Error> {
+        let depth = pack.ret.size();
+        self.run_until(pack, |pack| pack.ret.size() <= depth)
+    }
+
+    /// Run until the current block returns.
+    pub fn step_out(&mut self, pack: &mut Pack) -> Result<Pause, Error> {
+        let depth = pack.ret.size();
+        self.run_until(pack, |pack| pack.ret.size() < depth)
+    }
+
+    /// Run until a breakpoint, a watch or the end of the Concat.
+    pub fn cont(&mut self, pack: &mut Pack) -> Result<Pause, Error> {
+        self.run_until(pack, |_| false)
+    }
+
+    /// Cell that will be executed in the next step.
+    pub fn current_cell<'a>(&self, pack: &'a Pack) -> Option<&'a Cell> {
+        pack.concat.array.get(pack.concat.pointer)
+    }
+
+    /// Source location of the cell that will be executed in the next step.
+    pub fn current_location(&self, pack: &Pack) -> Option<Location> {
+        pack.concat.location(pack.concat.pointer)
+    }
+
+    /// Defined word and block currently in execution.
+    pub fn current_block<'a>(&self, pack: &'a Pack) -> Option<(&'a String, BlockRef)> {
+        pack.dictionary.word_at(pack.concat.pointer)
+    }
+
+    /// Words in the call chain, from the innermost caller to the outermost.
+    pub fn backtrace<'a>(&self, pack: &'a Pack) -> Vec<&'a String> {
+        (0..pack.ret.size()).filter_map(|n| {
+            let ret_pos = pack.ret.get(n)?;
+            pack.dictionary.word_at(ret_pos.checked_sub(1)?).map(|(word, _)| word)
+        }).collect()
+    }
+
+    fn value(pack: &Pack, word: &str) -> Option<Cell> {
+        if let Some(DictEntry::Data(cell)) = pack.dictionary.dict.get(word) {
+            Some(cell.clone())
+        }
+        else {
+            None
+        }
+    }
+
+    fn is_breakpoint(&self, pack: &Pack) -> bool {
+        let pos = pack.concat.pointer;
+        if self.positions.contains(&pos) {
+            return true;
+        }
+        if let Some(location) = pack.concat.location(pos) {
+            if self.locations.contains(&location) {
+                return true;
+            }
+        }
+        // Defined words break when entering the block, the rest when called from the concat
+        self.words.iter().any(|word| {
+            match pack.dictionary.dict.get(word) {
+                Some(DictEntry::Defined(block)) => block.pos == pos,
+                _ => matches!(pack.concat.array.get(pos), Some(Cell::Word(w)) if w == word),
+            }
+        })
+    }
+
+    fn changed_watch(&mut self, pack: &Pack) -> Option<Pause> {
+        for (word, old) in self.watches.iter_mut() {
+            let new = Self::value(pack, word);
+            if new != *old {
+                let old = core::mem::replace(old, new.clone());
+                return Some(Pause::Watch { word: word.clone(), old, new });
+            }
+        }
+        None
+    }
+
+    fn run_until(&mut self, pack: &mut Pack, done: impl Fn(&Pack) -> bool) -> Result<Pause, Error> {
+        // Inlined words are never entered, so the words that inlined them are restored
+        for word in self.words.iter() {
+            pack.dictionary.deoptimize_users(word);
+        }
+        let pause = self.next_pause(pack, done);
+        self.paused = Some(pack.concat.pointer);
+        pause
+    }
+
+    fn next_pause(&mut self, pack: &mut Pack, done: impl Fn(&Pack) -> bool) -> Result<Pause, Error> {
+        let mut resumed = self.paused.take() == Some(pack.concat.pointer);
+        loop {
+            // Breakpoints are checked before executing the cell, except the one we are paused on
+            if !resumed && self.is_breakpoint(pack) {
+                return Ok(Pause::Breakpoint(pack.concat.pointer));
+            }
+            resumed = false;
+            if !pack.one_step()? {
+                return Ok(Pause::End);
+            }
+            if let Some(pause) = self.changed_watch(pack) {
+                return Ok(pause);
+            }
+            if done(pack) {
+                return Ok(Pause::Step);
+            }
+        }
+    }
+}
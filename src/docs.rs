@@ -0,0 +1,68 @@
+use super::core::Pack;
+use hashbrown::HashMap;
+use alloc::{vec::Vec, string::String};
+use core::fmt::Write;
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// Documentation of a word.
+pub struct Doc {
+    /// Stack effect, like `a b -> c`.
+    pub stack: String,
+    /// Description and usage example.
+    pub desc: String,
+}
+
+#[derive(Default, Debug, Clone)]
+/// Documentation registry, filled by the `?` word.
+pub struct Docs {
+    entries: HashMap<String, Doc>,
+}
+
+impl Docs {
+    /// Set the documentation of a word.
+    pub fn set(&mut self, word: &str, stack: &str, desc: &str) {
+        self.entries.insert(word.into(), Doc { stack: stack.into(), desc: desc.into() });
+    }
+
+    /// Get the documentation of a word.
+    pub fn get(&self, word: &str) -> Option<&Doc> {
+        self.entries.get(word)
+    }
+
+    /// Stack effect of a word.
+    pub fn stack_effect(&self, word: &str) -> Option<&str> {
+        self.get(word).map(|doc| doc.stack.as_str())
+    }
+
+    /// Description of a word.
+    pub fn description(&self, word: &str) -> Option<&str> {
+        self.get(word).map(|doc| doc.desc.as_str())
+    }
+
+    /// Remove the documentation of a word.
+    pub fn remove(&mut self, word: &str) {
+        self.entries.remove(word);
+    }
+
+    /// All documented words, sorted by name.
+    pub fn words(&self) -> Vec<&String> {
+        let mut words: Vec<&String> = self.entries.keys().collect();
+        words.sort();
+        words
+    }
+
+    /// Render the vocabulary of a Pack as Markdown: every word in the dictionary, sorted by name, with its documentation.
+    pub fn markdown(&self, pack: &Pack) -> String {
+        let mut out = String::from("# Vocabulary\n");
+        for word in pack.dictionary.words() {
+            let _ = write!(out, "\n## {}\n\n", word);
+            if let Some(doc) = self.get(word) {
+                let _ = write!(out, "Stack Effects:\n\n```\n{}\n```\nDescription:\n\n```\n{}\n```\n", doc.stack, doc.desc);
+            }
+            else {
+                out.push_str("Undocumented.\n");
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,209 @@
+use super::core::{Pack, Cell, Error};
+use alloc::{string::String, vec::Vec};
+use core::fmt::Write;
+
+pub fn register_format(pack: &mut Pack) -> Result<(), Error> {
+    pack.def_natives(&[
+        (">str", to_str), ("fmt", fmt), (">int", to_int), (">float", to_float),
+    ])
+}
+
+/// Text representation of a cell.
+///
+/// Strings are rendered as they are, but inside blocks, lists and maps they are quoted, like in the source code.
+pub fn render(pack: &Pack, cell: &Cell) -> String {
+    match cell {
+        Cell::String(s) => s.clone(),
+        cell => {
+            let mut out = String::new();
+            render_into(pack, cell, &mut out);
+            out
+        },
+    }
+}
+
+fn render_into(pack: &Pack, cell: &Cell, out: &mut String) {
+    let _ = match cell {
+        Cell::Integer(i) => write!(out, "{}", i),
+        // Debug keeps the decimal point of round floats, like "1.0"
+        Cell::Float(f) => write!(out, "{:?}", f),
+        Cell::Boolean(b) => write!(out, "{}", b),
+        Cell::String(s) => write!(out, "'{}'", s.replace('\\', "\\\\").replace('\'', "\\'")),
+        Cell::Word(w) => write!(out, "{}", w),
+        Cell::Block(block) => {
+            out.push('{');
+            // The block cells include the closing curly
+            for cell in pack.concat.block(*block).unwrap_or(&[]) {
+                out.push(' ');
+                render_into(pack, cell, out);
+            }
+            Ok(())
+        },
+        Cell::Closure(closure) => {
+            render_into(pack, &Cell::Block(closure.block), out);
+            Ok(())
+        },
+        Cell::Quotation(quot) => {
+            out.push_str("quotation(");
+            for cell in quot.args.iter().chain(quot.calls.iter()) {
+                out.push(' ');
+                render_into(pack, cell, out);
+            }
+            out.push_str(" )");
+            Ok(())
+        },
+        Cell::List(list) => {
+            out.push_str("#(");
+            for cell in list {
+                out.push(' ');
+                render_into(pack, cell, out);
+            }
+            out.push_str(" )");
+            Ok(())
+        },
+        Cell::Map(map) => {
+            out.push_str("map(");
+            for (key, value) in map.entries() {
+                out.push(' ');
+                render_into(pack, key, out);
+                out.push(' ');
+                render_into(pack, value, out);
+            }
+            out.push_str(" )");
+            Ok(())
+        },
+        Cell::Struct(s) => match s.object.display() {
+            Some(text) => write!(out, "{}", text),
+            None => write!(out, "struct({})", s.name),
+        },
+        Cell::Continuation(_) => write!(out, "continuation"),
+    };
+}
+
+/// Maximum precision of a float placeholder, to avoid building huge strings.
+const MAX_PRECISION: usize = 100;
+
+/// Placeholder of a template: a cell, with optional float precision.
+struct Placeholder {
+    precision: Option<usize>,
+}
+
+/// Split a template into literal texts and placeholders. The texts are one more than the placeholders.
+fn parse_template(template: &str) -> Result<(Vec<String>, Vec<Placeholder>), Error> {
+    let mut texts = vec![String::new()];
+    let mut placeholders = Vec::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                texts.last_mut().unwrap().push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                texts.last_mut().unwrap().push('}');
+            },
+            '{' => {
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                        None => return Err(Error::new("fmt: Unclosed placeholder".into())),
+                    }
+                }
+                let precision = if spec.is_empty() {
+                    None
+                }
+                else {
+                    match spec.strip_prefix(":.").and_then(|n| n.parse::<usize>().ok()) {
+                        Some(precision) if precision > MAX_PRECISION => {
+                            return Err(Error::new(format!("fmt: Precision {} is too big, the maximum is {}", precision, MAX_PRECISION)));
+                        },
+                        Some(precision) => Some(precision),
+                        None => return Err(Error::new(format!("fmt: Invalid placeholder {{{}}}", spec))),
+                    }
+                };
+                placeholders.push(Placeholder { precision });
+                texts.push(String::new());
+            },
+            '}' => return Err(Error::new("fmt: Unmatched '}', use '}}' to write it".into())),
+            c => texts.last_mut().unwrap().push(c),
+        }
+    }
+    Ok((texts, placeholders))
+}
+
+fn to_str(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(cell) = pack.stack.pop() {
+        let text = render(pack, &cell);
+        pack.stack.push(text.into());
+        Ok(true)
+    }
+    else {
+        Err(Error::new(">str: Stack is empty".into()))
+    }
+}
+
+fn fmt(pack: &mut Pack) -> Result<bool, Error> {
+    // The cells are only taken from the stack once the whole string is formatted, so they are kept on error
+    let template = match pack.stack.get(0) {
+        Some(Cell::String(template)) => template,
+        _ => return Err(Error::new("fmt: Couldn't get a template string from the stack".into())),
+    };
+    let (texts, placeholders) = parse_template(template)?;
+    let count = placeholders.len();
+    if count >= pack.stack.size() {
+        return Err(Error::new(format!("fmt: The template needs {} cells, but the stack has {}", count, pack.stack.size() - 1)));
+    }
+    let mut out = texts[0].clone();
+    for (i, (placeholder, text)) in placeholders.iter().zip(texts[1..].iter()).enumerate() {
+        // The first placeholder takes the deepest cell, right below the template is the last one
+        let cell = match pack.stack.get(count - i) {
+            Some(cell) => cell,
+            None => return Err(Error::new("fmt: Couldn't get a cell from the stack".into())),
+        };
+        match (placeholder.precision, cell) {
+            (Some(precision), Cell::Float(f)) => {
+                let _ = write!(out, "{:.*}", precision, f);
+            },
+            (Some(precision), Cell::Integer(i)) => {
+                let _ = write!(out, "{:.*}", precision, *i as f64);
+            },
+            (Some(_), _) => return Err(Error::new(format!("fmt: Precision can only be used with numbers, found {}", render(pack, cell)))),
+            (None, cell) => out.push_str(&render(pack, cell)),
+        }
+        out.push_str(text);
+    }
+    for _ in 0..=count {
+        pack.stack.pop();
+    }
+    pack.stack.push(out.into());
+    Ok(true)
+}
+
+fn to_int(pack: &mut Pack) -> Result<bool, Error> {
+    let int = match pack.stack.pop() {
+        Some(Cell::Integer(i)) => i,
+        // Casting would saturate the out of range floats, and turn NaN into 0
+        Some(Cell::Float(f)) if (-9223372036854775808.0..9223372036854775808.0).contains(&f) => f as i64,
+        Some(Cell::Float(f)) => return Err(Error::new(format!(">int: The float {} can't be converted to an integer", f))),
+        Some(Cell::String(s)) => s.trim().parse::<i64>()
+            .map_err(|_| Error::new(format!(">int: Couldn't parse '{}' as an integer", s)))?,
+        _ => return Err(Error::new(">int: Couldn't get a string or a number from the stack".into())),
+    };
+    pack.stack.push(int.into());
+    Ok(true)
+}
+
+fn to_float(pack: &mut Pack) -> Result<bool, Error> {
+    let float = match pack.stack.pop() {
+        Some(Cell::Float(f)) => f,
+        Some(Cell::Integer(i)) => i as f64,
+        Some(Cell::String(s)) => s.trim().parse::<f64>()
+            .map_err(|_| Error::new(format!(">float: Couldn't parse '{}' as a float", s)))?,
+        _ => return Err(Error::new(">float: Couldn't get a string or a number from the stack".into())),
+    };
+    pack.stack.push(float.into());
+    Ok(true)
+}
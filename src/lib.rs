@@ -5,13 +5,35 @@
 
 #[macro_use]
 extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
 
 mod core;
 mod primitives;
 mod prelude;
 mod run_future;
+mod debugger;
+mod profiler;
+mod coverage;
+mod modules;
+mod docs;
+mod analysis;
+mod optimizer;
+mod list;
+mod map;
+mod combinators;
+mod strings;
+mod format;
 
 pub use self::core::*;
 pub use self::primitives::register_primitives;
+pub use self::debugger::*;
+pub use self::profiler::*;
+pub use self::coverage::*;
+pub use self::modules::*;
+pub use self::docs::*;
+pub use self::analysis::*;
+pub use self::optimizer::*;
+pub use self::format::render;
 
 //TODO: tests
\ No newline at end of file
@@ -0,0 +1,133 @@
+use super::core::{Pack, Cell, Error};
+use alloc::vec::Vec;
+
+pub fn register_list(pack: &mut Pack) -> Result<(), Error> {
+    pack.def_natives(&[
+        ("list", list), ("spread", spread), ("list.len", len), ("list.get", get), ("list.set", set), ("list.push", push),
+        ("list.pop", pop), ("list.slice", slice), ("list.concat", concat), ("list.reverse", reverse),
+        ("list.contains?", contains), ("list.index_of", index_of),
+    ])
+}
+
+/// Convert an integer into a position of a list of length `len`.
+fn position(n: i64, len: usize) -> Option<usize> {
+    usize::try_from(n).ok().filter(|pos| *pos < len)
+}
+
+fn pop_list(pack: &mut Pack, word: &str) -> Result<Vec<Cell>, Error> {
+    if let Some(Cell::List(list)) = pack.stack.pop() {
+        Ok(list)
+    }
+    else {
+        Err(Error::new(format!("{}: Couldn't get a list from the stack", word)))
+    }
+}
+
+fn pop_index(pack: &mut Pack, word: &str) -> Result<i64, Error> {
+    if let Some(Cell::Integer(n)) = pack.stack.pop() {
+        Ok(n)
+    }
+    else {
+        Err(Error::new(format!("{}: Couldn't get an integer from the stack", word)))
+    }
+}
+
+fn pop_cell(pack: &mut Pack, word: &str) -> Result<Cell, Error> {
+    pack.stack.pop().ok_or_else(|| Error::new(format!("{}: Stack is empty", word)))
+}
+
+fn list(pack: &mut Pack) -> Result<bool, Error> {
+    let cells = pack.stack.take();
+    pack.stack.push(cells.into());
+    Ok(true)
+}
+
+fn spread(pack: &mut Pack) -> Result<bool, Error> {
+    for cell in pop_list(pack, "spread")? {
+        pack.stack.push(cell);
+    }
+    Ok(true)
+}
+
+fn len(pack: &mut Pack) -> Result<bool, Error> {
+    let list = pop_list(pack, "list.len")?;
+    pack.stack.push((list.len() as i64).into());
+    Ok(true)
+}
+
+fn get(pack: &mut Pack) -> Result<bool, Error> {
+    let index = pop_index(pack, "list.get")?;
+    let mut list = pop_list(pack, "list.get")?;
+    let pos = position(index, list.len()).ok_or_else(|| Error::new(format!("list.get: Index {} out of range", index)))?;
+    pack.stack.push(list.swap_remove(pos));
+    Ok(true)
+}
+
+fn set(pack: &mut Pack) -> Result<bool, Error> {
+    let cell = pop_cell(pack, "list.set")?;
+    let index = pop_index(pack, "list.set")?;
+    let mut list = pop_list(pack, "list.set")?;
+    let pos = position(index, list.len()).ok_or_else(|| Error::new(format!("list.set: Index {} out of range", index)))?;
+    list[pos] = cell;
+    pack.stack.push(list.into());
+    Ok(true)
+}
+
+fn push(pack: &mut Pack) -> Result<bool, Error> {
+    let cell = pop_cell(pack, "list.push")?;
+    let mut list = pop_list(pack, "list.push")?;
+    list.push(cell);
+    pack.stack.push(list.into());
+    Ok(true)
+}
+
+fn pop(pack: &mut Pack) -> Result<bool, Error> {
+    let mut list = pop_list(pack, "list.pop")?;
+    let cell = list.pop().ok_or_else(|| Error::new("list.pop: List is empty".into()))?;
+    pack.stack.push(list.into());
+    pack.stack.push(cell);
+    Ok(true)
+}
+
+fn slice(pack: &mut Pack) -> Result<bool, Error> {
+    let end = pop_index(pack, "list.slice")?;
+    let start = pop_index(pack, "list.slice")?;
+    let list = pop_list(pack, "list.slice")?;
+    match (usize::try_from(start), usize::try_from(end)) {
+        (Ok(start), Ok(end)) if start <= end && end <= list.len() => {
+            pack.stack.push(list[start..end].to_vec().into());
+            Ok(true)
+        },
+        _ => Err(Error::new(format!("list.slice: Range {}..{} out of bounds", start, end))),
+    }
+}
+
+fn concat(pack: &mut Pack) -> Result<bool, Error> {
+    let b = pop_list(pack, "list.concat")?;
+    let mut a = pop_list(pack, "list.concat")?;
+    a.extend(b);
+    pack.stack.push(a.into());
+    Ok(true)
+}
+
+fn reverse(pack: &mut Pack) -> Result<bool, Error> {
+    let mut list = pop_list(pack, "list.reverse")?;
+    list.reverse();
+    pack.stack.push(list.into());
+    Ok(true)
+}
+
+fn contains(pack: &mut Pack) -> Result<bool, Error> {
+    let cell = pop_cell(pack, "list.contains?")?;
+    let list = pop_list(pack, "list.contains?")?;
+    pack.stack.push(list.contains(&cell).into());
+    Ok(true)
+}
+
+fn index_of(pack: &mut Pack) -> Result<bool, Error> {
+    let cell = pop_cell(pack, "list.index_of")?;
+    let list = pop_list(pack, "list.index_of")?;
+    let index = list.iter().position(|c| *c == cell).map(|pos| pos as i64).unwrap_or(-1);
+    pack.stack.push(index.into());
+    Ok(true)
+}
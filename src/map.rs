@@ -0,0 +1,91 @@
+use super::core::{Pack, Cell, Error, Map};
+use alloc::vec::Vec;
+
+pub fn register_map(pack: &mut Pack) -> Result<(), Error> {
+    pack.def_natives(&[
+        ("map.new", new), ("map.set", set), ("map.get", get), ("map.has?", has), ("map.remove", remove),
+        ("map.keys", keys), ("map.values", values), ("map.size", size), ("map.merge", merge),
+    ])
+}
+
+fn pop_map(pack: &mut Pack, word: &str) -> Result<Map, Error> {
+    if let Some(Cell::Map(map)) = pack.stack.pop() {
+        Ok(map)
+    }
+    else {
+        Err(Error::new(format!("{}: Couldn't get a map from the stack", word)))
+    }
+}
+
+fn pop_key(pack: &mut Pack, word: &str) -> Result<Cell, Error> {
+    match pack.stack.pop() {
+        Some(key) if Map::is_key(&key) => Ok(key),
+        Some(key) => Err(Error::new(format!("{}: Invalid key {:?}", word, key))),
+        None => Err(Error::new(format!("{}: Stack is empty", word))),
+    }
+}
+
+fn new(pack: &mut Pack) -> Result<bool, Error> {
+    pack.stack.push(Map::default().into());
+    Ok(true)
+}
+
+fn set(pack: &mut Pack) -> Result<bool, Error> {
+    let value = pack.stack.pop().ok_or_else(|| Error::new("map.set: Stack is empty".into()))?;
+    let key = pop_key(pack, "map.set")?;
+    let mut map = pop_map(pack, "map.set")?;
+    map.insert(key, value);
+    pack.stack.push(map.into());
+    Ok(true)
+}
+
+fn get(pack: &mut Pack) -> Result<bool, Error> {
+    let key = pop_key(pack, "map.get")?;
+    let mut map = pop_map(pack, "map.get")?;
+    let value = map.remove(&key).ok_or_else(|| Error::new(format!("map.get: Key {:?} not found", key)))?;
+    pack.stack.push(value);
+    Ok(true)
+}
+
+fn has(pack: &mut Pack) -> Result<bool, Error> {
+    let key = pop_key(pack, "map.has?")?;
+    let map = pop_map(pack, "map.has?")?;
+    pack.stack.push(map.contains_key(&key).into());
+    Ok(true)
+}
+
+fn remove(pack: &mut Pack) -> Result<bool, Error> {
+    let key = pop_key(pack, "map.remove")?;
+    let mut map = pop_map(pack, "map.remove")?;
+    map.remove(&key);
+    pack.stack.push(map.into());
+    Ok(true)
+}
+
+fn keys(pack: &mut Pack) -> Result<bool, Error> {
+    let map = pop_map(pack, "map.keys")?;
+    let keys: Vec<Cell> = map.entries().into_iter().map(|(key, _)| key.clone()).collect();
+    pack.stack.push(keys.into());
+    Ok(true)
+}
+
+fn values(pack: &mut Pack) -> Result<bool, Error> {
+    let map = pop_map(pack, "map.values")?;
+    let values: Vec<Cell> = map.entries().into_iter().map(|(_, value)| value.clone()).collect();
+    pack.stack.push(values.into());
+    Ok(true)
+}
+
+fn size(pack: &mut Pack) -> Result<bool, Error> {
+    let map = pop_map(pack, "map.size")?;
+    pack.stack.push((map.len() as i64).into());
+    Ok(true)
+}
+
+fn merge(pack: &mut Pack) -> Result<bool, Error> {
+    let b = pop_map(pack, "map.merge")?;
+    let mut a = pop_map(pack, "map.merge")?;
+    a.merge(b);
+    pack.stack.push(a.into());
+    Ok(true)
+}
@@ -0,0 +1,99 @@
+use super::core::Error;
+use hashbrown::{HashMap, HashSet};
+use alloc::{vec::Vec, string::String, rc::Rc, format};
+use core::cell::RefCell;
+#[cfg(feature = "std")]
+use std::path::PathBuf;
+
+/// Source provider for the modules loaded with `require`.
+pub trait ModuleResolver {
+    /// Get the source code of a module.
+    fn resolve(&mut self, name: &str) -> Result<String, Error>;
+}
+
+#[derive(Default, Debug, Clone)]
+/// Module resolver that serves sources from memory.
+pub struct MemoryResolver {
+    pub sources: HashMap<String, String>,
+}
+
+impl MemoryResolver {
+    /// Create an empty resolver.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the source of a module.
+    pub fn add(&mut self, name: &str, source: &str) {
+        self.sources.insert(name.into(), source.into());
+    }
+}
+
+impl ModuleResolver for MemoryResolver {
+    fn resolve(&mut self, name: &str) -> Result<String, Error> {
+        match self.sources.get(name) {
+            Some(source) => Ok(source.clone()),
+            None => Err(Error::new(format!("require: Module '{}' not found", name))),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+/// Module resolver that reads sources from files. Module `a.b` is the file `a/b.rp` inside the root directory.
+pub struct FileResolver {
+    pub root: PathBuf,
+}
+
+#[cfg(feature = "std")]
+impl FileResolver {
+    /// Create a resolver that reads modules from the `root` directory.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ModuleResolver for FileResolver {
+    fn resolve(&mut self, name: &str) -> Result<String, Error> {
+        // Each segment must be a plain file name, so a module can't be read from outside the root
+        let valid = |segment: &str| !segment.is_empty() && segment != ".." && !segment.contains(['/', '\\']) && !std::path::Path::new(segment).is_absolute();
+        if !name.split('.').all(valid) {
+            return Err(Error::new(format!("require: Invalid module name '{}'", name)));
+        }
+        let mut path = self.root.clone();
+        path.extend(name.split('.'));
+        path.set_extension("rp");
+        std::fs::read_to_string(&path).map_err(|e| Error::new(format!("require: Couldn't read module '{}' from {}: {}", name, path.display(), e)))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A module being loaded, with the lexicon state to restore when it finishes.
+pub struct Loading {
+    pub name: String,
+    pub lex: String,
+    pub imports: Vec<String>,
+    /// Number of lexicons open when the module was required.
+    pub depth: usize,
+}
+
+#[derive(Default, Clone)]
+/// Module loading state.
+pub struct Modules {
+    pub resolver: Option<Rc<RefCell<dyn ModuleResolver>>>,
+    pub loaded: HashSet<String>,
+    pub loading: Vec<Loading>,
+}
+
+impl Modules {
+    /// Set the resolver used by `require`.
+    pub fn set_resolver(&mut self, resolver: impl ModuleResolver + 'static) {
+        self.resolver = Some(Rc::new(RefCell::new(resolver)));
+    }
+
+    /// Check if a module was already loaded.
+    pub fn is_loaded(&self, name: &str) -> bool {
+        self.loaded.contains(name)
+    }
+}
@@ -0,0 +1,278 @@
+use super::core::{Pack, Cell, DictEntry, Optimized, UNSCOPED};
+use super::analysis::parsed_cells;
+use alloc::{vec::Vec, string::String};
+
+/// Maximum number of cells of a word body to be inlined.
+const INLINE_LIMIT: usize = 8;
+
+/// Maximum depth of nested inlining.
+const INLINE_DEPTH: usize = 4;
+
+/// Words that make a body not inlinable, because they depend on its concat position or the caller's.
+const NOT_INLINABLE: &[&str] = &["@@", "skip", "loop", "again", "leave", "while", "do", "callcc", "{", "}", "|"];
+
+/// Arithmetic primitives that can be folded when applied to literals.
+const FOLDABLE: &[&str] = &["+", "-", "*", "/", "%"];
+
+#[derive(Debug, Clone)]
+/// Cell of an optimized block, with the concat position of the cell it comes from.
+struct OptCell {
+    cell: Cell,
+    orig: usize,
+    /// The cell is taken from the concat by the previous word, so it can't be changed.
+    fixed: bool,
+}
+
+struct Optimizer<'a> {
+    pack: &'a Pack,
+    deps: Vec<String>,
+    changed: bool,
+}
+
+impl Optimizer<'_> {
+    fn word(&self, pos: usize) -> Option<&str> {
+        match self.pack.concat.array.get(pos) {
+            Some(Cell::Word(w)) => Some(w.as_str()),
+            _ => None,
+        }
+    }
+
+    fn add_dep(&mut self, word: &str) {
+        if !self.deps.iter().any(|dep| dep == word) {
+            self.deps.push(word.into());
+        }
+        // The optimization of an inlined word depends on the same words
+        if let Some(optimized) = self.pack.dictionary.optimized(word) {
+            for dep in optimized.deps.iter() {
+                if !self.deps.contains(dep) {
+                    self.deps.push(dep.clone());
+                }
+            }
+        }
+    }
+
+    /// Body of a defined word, if it's small enough and can be inlined.
+    fn inlinable(&self, word: &str, chain: &[String]) -> Option<(usize, usize)> {
+        if chain.len() >= INLINE_DEPTH || chain.iter().any(|w| w == word) {
+            return None;
+        }
+        if let Some(DictEntry::Defined(block)) = self.pack.dictionary.dict.get(word) {
+            let (start, end) = (block.pos, block.pos + block.len - 1);
+            if end - start > INLINE_LIMIT {
+                return None;
+            }
+            let blocked = self.pack.concat.array[start..end].iter().any(|cell| {
+                matches!(cell, Cell::Word(w) if NOT_INLINABLE.contains(&w.as_str()))
+            });
+            if !blocked {
+                return Some((start, end));
+            }
+        }
+        None
+    }
+
+    /// Optimize the cells between `start` and `end`, appending them to `out`. `chain` are the words being inlined.
+    fn body(&mut self, start: usize, end: usize, chain: &mut Vec<String>, out: &mut Vec<OptCell>) {
+        let mut pos = start;
+        let mut fixed = 0;
+        while pos < end {
+            let cell = self.pack.concat.array[pos].clone();
+            if fixed > 0 {
+                fixed -= 1;
+                out.push(OptCell { cell, orig: pos, fixed: true });
+                pos += 1;
+                continue;
+            }
+            let word = match self.word(pos) {
+                Some(word) => word,
+                None => {
+                    out.push(OptCell { cell, orig: pos, fixed: false });
+                    pos += 1;
+                    continue;
+                },
+            };
+            match word {
+                "[" => {
+                    let len = self.pack.concat.array[pos..end].iter().position(|cell| matches!(cell, Cell::Word(w) if w == "]"));
+                    if let Some(len) = len {
+                        if let Some(code) = self.compile_transfer(pos + 1, pos + len) {
+                            self.changed = true;
+                            out.push(OptCell { cell: Cell::Word("shuffle".into()), orig: pos, fixed: false });
+                            out.push(OptCell { cell: Cell::Integer(code), orig: pos, fixed: true });
+                            pos += len + 1;
+                            continue;
+                        }
+                        fixed = len;
+                    }
+                },
+                // Nested blocks are kept as they are
+                "{" => {
+                    let mut level = 0;
+                    for (i, cell) in self.pack.concat.array[pos..end].iter().enumerate() {
+                        match cell {
+                            Cell::Word(w) if w == "{" => level += 1,
+                            Cell::Word(w) if w == "}" => {
+                                level -= 1;
+                                if level == 0 {
+                                    fixed = i;
+                                    break;
+                                }
+                            },
+                            _ => {},
+                        }
+                    }
+                },
+                "if" => fixed = 2,
+                "while" => fixed = 3,
+                "do" => fixed = 1,
+                _ => {
+                    if !parsed_cells(word).is_empty() {
+                        fixed = parsed_cells(word).len();
+                    }
+                    else if let Some(resolved) = self.pack.resolve(word, pos) {
+                        if let Some((body_start, body_end)) = self.inlinable(&resolved, chain) {
+                            let resolved = resolved.into_owned();
+                            self.add_dep(&resolved);
+                            self.changed = true;
+                            chain.push(resolved);
+                            self.body(body_start, body_end, chain, out);
+                            chain.pop();
+                            pos += 1;
+                            continue;
+                        }
+                    }
+                },
+            }
+            out.push(OptCell { cell, orig: pos, fixed: false });
+            pos += 1;
+        }
+    }
+
+    /// Compile the stack transfer between `start` and `end` (the words between `[` and `]`) into a `shuffle` code.
+    fn compile_transfer(&self, start: usize, end: usize) -> Option<i64> {
+        let words: Vec<&str> = (start..end).map(|pos| self.word(pos)).collect::<Option<_>>()?;
+        let split = words.iter().position(|w| *w == "|")?;
+        let (inputs, outputs) = (&words[..split], &words[split + 1..]);
+        if inputs.len() > 15 || outputs.len() > 14 {
+            return None;
+        }
+        let mut code = inputs.len() as u64 | (outputs.len() as u64) << 4;
+        for (i, output) in outputs.iter().enumerate() {
+            // Like in a stack transfer, a repeated input name refers to the last cell taken with it
+            let index = inputs.iter().rposition(|input| input == output)? as u64;
+            code |= index << (8 + 4 * i);
+        }
+        Some(code as i64)
+    }
+
+    /// Fold arithmetic operations applied to two literals.
+    fn fold(&mut self, cells: &mut Vec<OptCell>) {
+        let mut i = 0;
+        while i + 2 < cells.len() {
+            if let Some(result) = self.fold_at(cells, i) {
+                self.changed = true;
+                let orig = cells[i].orig;
+                cells.splice(i..i + 3, [OptCell { cell: result, orig, fixed: false }]);
+                // The result may be the operand of a previous operation
+                i = i.saturating_sub(2);
+            }
+            else {
+                i += 1;
+            }
+        }
+    }
+
+    fn fold_at(&mut self, cells: &[OptCell], i: usize) -> Option<Cell> {
+        let (a, b, op) = (&cells[i], &cells[i + 1], &cells[i + 2]);
+        if a.fixed || b.fixed || op.fixed {
+            return None;
+        }
+        let op_word = match &op.cell {
+            Cell::Word(w) if FOLDABLE.contains(&w.as_str()) => w.as_str(),
+            _ => return None,
+        };
+        // Only the primitive operations, not redefinitions
+        let resolved = self.pack.resolve(op_word, op.orig)?;
+        if resolved != op_word || !matches!(self.pack.dictionary.dict.get(op_word), Some(DictEntry::Native(_))) {
+            return None;
+        }
+        let result = match (&a.cell, &b.cell) {
+            (Cell::Integer(a), Cell::Integer(b)) => match op_word {
+                "+" => a.checked_add(*b),
+                "-" => a.checked_sub(*b),
+                "*" => a.checked_mul(*b),
+                "/" => a.checked_div(*b),
+                _ => a.checked_rem(*b),
+            }.map(Cell::Integer),
+            (Cell::Float(a), Cell::Float(b)) if *b != 0.0 || matches!(op_word, "+" | "-" | "*") => match op_word {
+                "+" => Some(a + b),
+                "-" => Some(a - b),
+                "*" => Some(a * b),
+                "/" => Some(a / b),
+                _ => Some(a % b),
+            }.map(Cell::Float),
+            (Cell::String(a), Cell::String(b)) if op_word == "+" => Some(Cell::String(a.clone() + b)),
+            _ => None,
+        }?;
+        self.add_dep(op_word);
+        Some(result)
+    }
+}
+
+/// Optimize a defined word: inline small defined words, fold arithmetic on literals and compile stack transfers.
+///
+/// The optimized body is appended to the Concat and replaces the definition. If any inlined word is redefined later,
+/// the original definition is restored. The original definition also runs while the Pack has hooks or a restricted
+/// sandbox, so the inlined words are still observed and checked. Sealed words are not optimized. Returns true if the
+/// word was optimized.
+pub fn optimize_word(pack: &mut Pack, word: &str) -> bool {
+    if pack.dictionary.is_sealed(word) {
+        return false;
+    }
+    let original = match pack.dictionary.optimized(word) {
+        Some(optimized) => optimized.original,
+        None => match pack.dictionary.dict.get(word) {
+            Some(DictEntry::Defined(block)) => *block,
+            _ => return false,
+        },
+    };
+    let (start, end) = (original.pos, original.pos + original.len - 1);
+    // Bodies that skip cells depend on their length, and local variables may shadow the words to inline
+    if pack.concat.array[start..end].iter().any(|cell| matches!(cell, Cell::Word(w) if w == "skip" || w == "|")) {
+        return false;
+    }
+    let mut optimizer = Optimizer { pack, deps: Vec::new(), changed: false };
+    let mut cells = Vec::new();
+    optimizer.body(start, end, &mut vec![String::from(word)], &mut cells);
+    optimizer.fold(&mut cells);
+    let (changed, deps) = (optimizer.changed, optimizer.deps);
+    if !changed {
+        return false;
+    }
+    let (scopes, locations): (Vec<usize>, Vec<_>) = cells.iter().map(|c| {
+        (pack.concat.scopes.get(c.orig).copied().unwrap_or(UNSCOPED), pack.concat.location(c.orig))
+    }).unzip();
+    let mut cells: Vec<Cell> = cells.into_iter().map(|c| c.cell).collect();
+    cells.push(Cell::Word("}".into()));
+    let block = pack.append_block(cells);
+    // Keep the scope and source location of the original cells
+    let len = pack.concat.array.len();
+    pack.concat.scopes.resize(len, UNSCOPED);
+    pack.concat.locations.resize(len, None);
+    for (i, (scope, location)) in scopes.into_iter().zip(locations).enumerate() {
+        pack.concat.scopes[block.pos + i] = scope;
+        pack.concat.locations[block.pos + i] = location;
+    }
+    pack.dictionary.set_optimized(word, Optimized { original, block, deps }).is_ok()
+}
+
+/// Optimize all defined words. Returns the optimized words, sorted by name.
+pub fn optimize(pack: &mut Pack) -> Vec<String> {
+    let mut words: Vec<String> = pack.dictionary.dict.iter()
+        .filter(|(_, entry)| matches!(entry, DictEntry::Defined(_)))
+        .map(|(word, _)| word.clone())
+        .collect();
+    words.sort();
+    words.retain(|word| optimize_word(pack, word));
+    words
+}
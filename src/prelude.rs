@@ -3,9 +3,23 @@ pub const PRELUDE: &str = r#"
 
     ? ( ' -> ' 'Open new nested stack: 1 2 ( 3 4 )'
     ? ) ' -> ' 'Close nested stack.'
+    ? #( ' -> ' 'Open new nested stack that becomes a list when closed: #( 1 2 3 )'
     ? { ' -> a' 'Create a block and put block cell in the stack: { 1 + }'
     ? } ' -> ' 'Return from block, get concat position from the return stack.'
     ? [ '? -> ?' 'Create stack transfer: [ a b | a a ]'
+    ? drop 'a -> ' 'Extract one cell from the stack.'
+    ? dup 'a -> a a' 'Duplicate a cell in the stack.'
+    ? swap 'a b -> b a' 'Swap positions of 2 cells in the stack.'
+    ? over 'a b -> a b a' 'Copy the second cell to the top of the stack: 1 2 over'
+    ? rot 'a b c -> b c a' 'Rotate the third cell to the top of the stack: 1 2 3 rot'
+    ? nip 'a b -> b' 'Remove the second cell from the stack: 1 2 nip'
+    ? tuck 'a b -> b a b' 'Copy the top cell below the second one: 1 2 tuck'
+    ? pick '... n -> ... x' 'Copy the cell at position "n" from the top (0 is the top) to the top of the stack: 1 2 3 2 pick'
+    ? roll '... n -> ... x' 'Move the cell at position "n" from the top (0 is the top) to the top of the stack: 1 2 3 2 roll'
+    ? | '... -> ' 'Define local variables of a block, taking their values from the stack, the first name takes the top. Read them by name and assign them with name!: { | b a | a b - } def minus'
+    ? closure 'a -> b' 'Create a closure from block "a", capturing the values of the local variables visible: { | n | { n + } closure } def adder 10 adder'
+    ? capture '... a -> b' 'Create a closure from block "a", capturing its local variables from the stack: 10 { | n | n + } capture'
+    ? shuffle '? -> ?' 'Compiled stack transfer, created by the optimizer. Gets the permutation from the concat as an integer.'
     ? _ ' -> ' 'It does nothing: _'
     ? skip 'a -> ' 'Skip "a" words from the concat: 1 skip wont_be_executed'
     ? size ' -> a' 'Get size of current stack: size'
@@ -26,18 +40,25 @@ pub const PRELUDE: &str = r#"
     ? or 'a b -> c' 'Calculate logic "or" of two operands: -1 0 or'
     ? not 'a -> b' 'Calculate logic inversion of an operand: 0 not'
     ? if 'a -> ' 'Get a boolean from the stack and executes one of the 2 next words in the concat: condition if word_true word_false'
-    ? either 'a b c -> ' 'Execute block b if a is true, or block c if a is false: 2 2 = { "true block" } { "false block" } either'
+    ? either 'a b c -> ' 'Execute block b if a is true, or block c if a is false. They can also be closures, quotations or word references: 2 2 = { "true block" } { "false block" } either'
     ? exe 'a -> ' 'Execute a word referenced in the stack: @ a_word exe'
     ? int 'a -> b' 'Convert a float into an integer: 10.9 int'
     ? float 'a -> b' 'Convert an integer into a float: 10 float'
     ? string 'a -> b' 'Convert a word into a string: @ my_word string'
+    ? >str 'a -> b' 'Convert any cell into a string: 3.14 >str'
+    ? >int 'a -> b' 'Parse a string into an integer, or convert a number. Floats are truncated, NaN, infinite and out of range floats fail: \'42\' >int'
+    ? >float 'a -> b' 'Parse a string into a float, or convert a number: \'3.5\' >float'
+    ? fmt '? a -> b' 'Replace the placeholders of template "a" with cells from the stack, {} or {:.N} for floats, with N up to 100: 1 2.5 \'{} and {:.2}\' fmt'
     ? word 'a -> b' 'Convert a string into a word: \'my_word\' word'
     ? type 'a -> a b' 'Get type of data in the stack without consuming it: 20 type'
     ? @@ ' -> a' 'Get a cell from the concat of current block caller, and put it in the stack: { @@ } exe my_word'
     ? @def 'a b -> ' 'Define word b with value a: 10 @ my_num @def'
+    ? use ' -> ' 'Import a lexicon, to use its words without prefix: use domain num'
+    ? require ' -> ' 'Load a module once, running it inside its own lexicon: require \'math\' 2 math.square'
+    ? \require ' -> ' 'Finish loading a module, restoring the lexicon of the caller. Appended by require.'
     ? lex# ' -> a' 'Put value of current lex prefix in the stack: lex#'
     ? block '... a -> b' 'Get a block from the stack and create a new one. For each $ word in the block, it will get a cell from the stack and put in its place: 10 { 1 $ + } block exe'
-    ? exist? 'a -> a b' 'Check if word "a" exists and puts a boolean "b" in the stack: @ my_word exist?'
+    ? exist? 'a -> a b' 'Check if word "a" exists and is allowed by the sandbox, and puts a boolean "b" in the stack: @ my_word exist?'
     ? wipe 'a b c ... N -> ' 'Remove all cells in the stack: ( 1 2 3 wipe )'
     ? loop ' -> ' 'Put current concat position in the return stack: { loop \'Loop forever\' print } def endless'
     ? again 'a -> ' 'Get a boolean from the stack and an address from the return stack. If boolean is true, it jumps to the address: 10 loop dup print -- dup 0 > again drop'
@@ -48,7 +69,18 @@ pub const PRELUDE: &str = r#"
         10 countdown'
     ? do 'a -> ' 'Get a boolean from the stack, if true, jump to to the next word in the concat, otherwise remove an address from the return stack and skip one word. See \'while\' for a usage example.'
     ? leave 'a -> ' 'Discard "a" positions from the return stack, then use the Nth as a pointer to continue execution. Used to return from deep-nested blocks, where "a" is the depth: { loop \'Do it once\' print 1 leave } def doit_once'
-    ? ? ' -> ' 'Get a word and two strings from the concat and generate help words: ? add \'a b -> c\' \'Calculate addition of two operands and put results in stack.\''
+    ? callcc 'a -> ' 'Capture the current continuation (concat pointer and return stack), put it in the stack and execute block "a" (or a closure, quotation or word reference). Executing the continuation with exe resumes right after the callcc: { def return 1 return exe 2 } callcc'
+    ? seal 'a -> ' 'Seal word "a", so it cannot be redefined: @ my_word seal'
+    ? private ' -> ' 'Make a word private to the current lexicon, only usable from code in it: lex lib { } def helper private helper \lex'
+    ? forget ' -> ' 'Remove the word taken from the concat from the dictionary. Inside a lexicon the name gets its prefix, like with def: forget my_word'
+    ? marker ' -> ' 'Create a marker word with the name taken from the concat. Executing it removes all definitions made after the marker, restoring the previous ones: marker plugin 10 def num plugin'
+    ? rollback 'a -> ' 'Roll back the dictionary to the state it had when marker "a" was created. Used by marker words: \'plugin\' rollback'
+    ? words ' -> a b c ... N' 'Put all defined words in the stack, sorted by name: ( words size )'
+    ? lex_words 'a -> b c d ... N' 'Put all words in the lexicon with prefix "a" in the stack, sorted by name: ( \'math.\' lex_words )'
+    ? kind 'a -> a b' 'Get the kind of word "a", that can be native, defined or data: @ dup kind'
+    ? body 'a -> b' 'Get the block of defined word "a": 2.5 @ fract body exe'
+    ? help ' -> a b' 'Get a word from the concat and put its stack effect and description in the stack: help dup'
+    ? ? ' -> ' 'Get a word and two strings from the concat and register them as the word documentation: ? add \'a b -> c\' \'Calculate addition of two operands and put results in stack.\''
 
     "--- Word Definition ---"
 
@@ -64,17 +96,6 @@ pub const PRELUDE: &str = r#"
     ? var 'a -> ' 'Define a variable and a setter with intial value taken from the stack and name taken from the concat: 10 var num'
     { @@ [ word val | word val word ] @def setter } def var
 
-    "--- Stack ---"
-
-    ? drop 'a -> ' 'Extract one cell from the stack.'
-    { [ a | ] } def drop
-
-    ? dup 'a -> a a' 'Duplicate a cell in the stack.'
-    { [ a | a a ] } def dup
-
-    ? swap 'a b -> b a' 'Swap positions of 2 cells in the stack.'
-    { [ a b | a b ] } def swap
-
     "--- Types ---"
 
     ? is_int? 'a -> a bool' 'Check if cell in the stack is an integer: 10 is_int?'
@@ -97,6 +118,21 @@ pub const PRELUDE: &str = r#"
 
     ? is_struct? 'a -> a bool' 'Check if cell in the stack is a struct: custom_struct is_struct?'
     { type 'struct' = } def is_struct?
+
+    ? is_cont? 'a -> a bool' 'Check if cell in the stack is a continuation: { } callcc is_cont?'
+    { type 'continuation' = } def is_cont?
+
+    ? is_closure? 'a -> a bool' 'Check if cell in the stack is a closure: { } closure is_closure?'
+    { type 'closure' = } def is_closure?
+
+    ? is_quot? 'a -> a bool' 'Check if cell in the stack is a quotation: 1 { + } curry is_quot?'
+    { type 'quotation' = } def is_quot?
+
+    ? is_list? 'a -> a bool' 'Check if cell in the stack is a list: #( ) is_list?'
+    { type 'list' = } def is_list?
+
+    ? is_map? 'a -> a bool' 'Check if cell in the stack is a map: map.new is_map?'
+    { type 'map' = } def is_map?
     
     "--- Math ---"
 
@@ -110,14 +146,85 @@ pub const PRELUDE: &str = r#"
     { dup int float - } def fract
 
     ? add 'a b c .. N -> z' 'Calculate sumation of all numbers in the stack: ( 1 2 3 add )'
-    { loop + size 1 > again } def add
+    { { + } reduce } def add
 
     ? sub 'a b c .. N -> z' 'Calculate substraction of all numbers in the stack: ( 1 2 3 sub )'
-    { loop - size 1 > again } def sub
+    { list list.reverse { swap - } reduce } def sub
 
     ? mul 'a b c .. N -> z' 'Calculate product of all numbers in the stack: ( 1 2 3 mul )'
-    { loop * size 1 > again } def mul
+    { { * } reduce } def mul
 
     ? div 'a b c .. N -> z' 'Calculate division of all numbers in the stack: ( 3 6 2 div )'
-    { loop / size 1 > again } def div
-"#;
\ No newline at end of file
+    { list list.reverse { swap / } reduce } def div
+
+    "--- Sequences ---"
+
+    ? each '? a -> ?' 'Execute block "a" for each cell of a list, or of the current stack, leaving the results in the stack: #( 1 2 3 ) { print } each'
+    ? map '? a -> ?' 'Replace each cell of a list, or of the current stack, with the cells left by block "a": #( 1 2 3 ) { 2 * } map'
+    ? filter '? a -> ?' 'Keep the cells of a list, or of the current stack, for which block "a" returns true: ( 1 2 3 4 { 2 % 0 = } filter )'
+    ? fold '? a b -> c' 'Combine the cells of a list, or of the current stack, with block "b", starting with "a": #( 1 2 3 ) 0 { + } fold'
+    ? reduce '? a -> b' 'Combine the cells of a list, or of the current stack, with block "a", starting with the first cell: ( 1 2 3 { + } reduce )'
+    ? any '? a -> b' 'Check if block "a" returns true for any cell of a list, or of the current stack: #( 1 2 3 ) { 2 = } any'
+    ? all '? a -> b' 'Check if block "a" returns true for all cells of a list, or of the current stack: #( 1 2 3 ) { 0 > } all'
+    ? sort_by '? a -> ?' 'Sort the cells of a list, or of the current stack, by the key that block "a" returns for each one: #( 3 1 2 ) { } sort_by'
+
+    "--- Dataflow ---"
+
+    ? >r 'a -> ' 'Move a cell to the retain stack: 1 >r'
+    ? r> ' -> a' 'Move a cell from the retain stack: 1 >r r>'
+    ? dip 'a b -> ?' 'Execute block "b" without cell "a", and put "a" back: 1 2 { 10 + } dip'
+    ? keep 'a b -> ?' 'Execute block "b" with cell "a", and put "a" back: 5 { 2 * } keep'
+    ? bi 'a b c -> ?' 'Execute blocks "b" and "c", each with cell "a": 5 { 1 + } { 2 * } bi'
+    ? tri 'a b c d -> ?' 'Execute blocks "b", "c" and "d", each with cell "a": 5 { 1 + } { 2 * } { 3 - } tri'
+    ? bi* 'a b c d -> ?' 'Execute block "c" with cell "a", and block "d" with cell "b": 1 2 { 10 + } { 20 + } bi*'
+    ? bi@ 'a b c -> ?' 'Execute block "c" with cell "a", and then with cell "b": 1 2 { 10 * } bi@'
+    ? cleave 'a b -> ?' 'Execute each block of list "b" with cell "a": 5 #( { 1 + } { 2 * } ) cleave'
+    ? curry 'a b -> c' 'Create a quotation that puts cell "a" in the stack and executes block "b": 5 { + } curry'
+    ? compose 'a b -> c' 'Create a quotation that executes block "a" and then block "b": { 1 + } { 2 * } compose'
+
+    "--- Strings ---"
+
+    ? str.len 'a -> b' 'Get the number of chars of a string: \'hi\' str.len'
+    ? str.bytes 'a -> b' 'Get the number of bytes of a string: \'hi\' str.bytes'
+    ? str.slice 'a b c -> d' 'Get the chars of string "a" from index "b" to "c" (not included): \'hello\' 1 3 str.slice'
+    ? str.find 'a b -> c' 'Get the char index of string "b" in string "a", or -1 if not found: \'hello\' \'ll\' str.find'
+    ? str.replace 'a b c -> d' 'Replace all occurrences of string "b" in string "a" with "c": \'a-b\' \'-\' \'+\' str.replace'
+    ? str.split 'a b -> c' 'Split string "a" by separator "b" into a list, or into chars if "b" is empty. Use spread to get the parts in the stack: \'a,b\' \',\' str.split'
+    ? str.join '? a -> b' 'Join the strings of a list, or of the current stack, with separator "a": #( \'a\' \'b\' ) \',\' str.join'
+    ? str.trim 'a -> b' 'Remove whitespaces at the beginning and the end of a string: \' hi \' str.trim'
+    ? str.upper 'a -> b' 'Convert a string to uppercase: \'hi\' str.upper'
+    ? str.lower 'a -> b' 'Convert a string to lowercase: \'HI\' str.lower'
+    ? str.starts_with? 'a b -> c' 'Check if string "a" starts with string "b": \'hello\' \'he\' str.starts_with?'
+    ? str.ends_with? 'a b -> c' 'Check if string "a" ends with string "b": \'hello\' \'lo\' str.ends_with?'
+    ? str.repeat 'a b -> c' 'Repeat string "a" "b" times: \'ab\' 3 str.repeat'
+    ? str.char_at 'a b -> c' 'Get the char at index "b" of string "a", as a string: \'hello\' 1 str.char_at'
+    ? str.code 'a -> b' 'Get the code of the first char of a string: \'A\' str.code'
+    ? str.from_code 'a -> b' 'Get a string with the char of a code: 65 str.from_code'
+
+    "--- Lists ---"
+
+    ? list 'a b c .. N -> z' 'Collect all cells in the stack into a list: ( 1 2 3 list )'
+    ? spread 'a -> b c d .. N' 'Put all cells of a list in the stack: #( 1 2 3 ) spread'
+    ? list.len 'a -> b' 'Get the number of cells in a list: #( 1 2 3 ) list.len'
+    ? list.get 'a b -> c' 'Get the cell at index "b" of list "a": #( 1 2 3 ) 0 list.get'
+    ? list.set 'a b c -> d' 'Set the cell at index "b" of list "a" to "c": #( 1 2 3 ) 0 10 list.set'
+    ? list.push 'a b -> c' 'Add cell "b" at the end of list "a": #( 1 2 3 ) 4 list.push'
+    ? list.pop 'a -> b c' 'Remove the last cell of list "a": #( 1 2 3 ) list.pop'
+    ? list.slice 'a b c -> d' 'Get the cells of list "a" from index "b" to "c" (not included): #( 1 2 3 ) 1 3 list.slice'
+    ? list.concat 'a b -> c' 'Concatenate two lists: #( 1 2 ) #( 3 ) list.concat'
+    ? list.reverse 'a -> b' 'Reverse the order of a list: #( 1 2 3 ) list.reverse'
+    ? list.contains? 'a b -> c' 'Check if list "a" contains cell "b": #( 1 2 3 ) 2 list.contains?'
+    ? list.index_of 'a b -> c' 'Get the index of the first cell of list "a" equal to "b", or -1 if not found: #( 1 2 3 ) 2 list.index_of'
+
+    "--- Maps ---"
+
+    ? map.new ' -> a' 'Create an empty map: map.new'
+    ? map.set 'a b c -> d' 'Set the value of key "b" in map "a" to "c": map.new \'x\' 10 map.set'
+    ? map.get 'a b -> c' 'Get the value of key "b" in map "a": map.new \'x\' 10 map.set \'x\' map.get'
+    ? map.has? 'a b -> c' 'Check if map "a" has key "b": map.new \'x\' map.has?'
+    ? map.remove 'a b -> c' 'Remove key "b" from map "a": map.new \'x\' 10 map.set \'x\' map.remove'
+    ? map.keys 'a -> b' 'Get a list with the keys of a map, sorted: map.new \'x\' 10 map.set map.keys'
+    ? map.values 'a -> b' 'Get a list with the values of a map, sorted by key: map.new \'x\' 10 map.set map.values'
+    ? map.size 'a -> b' 'Get the number of entries of a map: map.new map.size'
+    ? map.merge 'a b -> c' 'Add the entries of map "b" to map "a", replacing existing keys: map.new map.new map.merge'
+"#;
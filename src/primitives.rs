@@ -1,29 +1,58 @@
-use super::core::{Pack, Cell, BlockRef, Stack, DictEntry, Error};
-use hashbrown::HashMap;
-use alloc::string::String;
-
-pub fn register_primitives(pack: &mut Pack) {
+use super::core::{Pack, Cell, BlockRef, Stack, DictEntry, Error, Transfer, Locals, Frame, Closure, INTERNAL, UNSCOPED};
+use super::modules::Loading;
+use super::list::register_list;
+use super::map::register_map;
+use super::combinators::register_combinators;
+use super::strings::register_strings;
+use super::format::register_format;
+use alloc::{string::String, vec::Vec, rc::Rc};
+
+pub fn register_primitives(pack: &mut Pack) -> Result<(), Error> {
     pack.def_natives(&[
-        ("(", open_parenth), (")", close_parenth), ("size", size), ("{", open_curly), ("}", close_curly), ("lex", lex),
+        ("(", open_parenth), ("#(", open_list), (")", close_parenth), ("size", size), ("{", open_curly), ("}", close_curly), ("lex", lex),
         ("\\lex", close_lex), ("+", plus), ("-", minus), ("*", star), ("/", slash), ("%", percent), (">", bigger), ("<", smaller),
         ("=", equal), ("!=", not_equal), (">=", big_equal), ("<=", small_equal), ("and", and), ("or", or), ("not", not),
-        ("wipe", wipe), ("if", if_word), ("either", either), ("[", open_bracket), ("exe", exe), ("int", int), ("float", float),
-        ("string", string), ("word", word), ("type", type_word), ("?", question), ("@@", atat), ("@def", atdef), ("lex#", lex_sharp),
+        ("wipe", wipe), ("if", if_word), ("either", either), ("[", open_bracket), ("|", locals), ("shuffle", shuffle), ("exe", exe), ("closure", closure), ("capture", capture), ("int", int),
+        ("float", float), ("string", string), ("word", word), ("type", type_word), ("?", question), ("help", help), ("@@", atat),
+        ("@def", atdef), ("lex#", lex_sharp),
         ("skip", skip), ("block", block), ("exist?", exist_question), ("_", underscore), ("leave", leave), ("loop", loop_word),
-        ("again", again), ("while", while_word), ("do", do_word),
-    ]);
+        ("again", again), ("while", while_word), ("do", do_word), ("callcc", callcc),
+        ("seal", seal), ("private", private), ("forget", forget), ("marker", marker), ("rollback", rollback),
+        ("use", use_word), ("require", require), ("\\require", close_require),
+        ("words", words), ("lex_words", lex_words), ("kind", kind), ("body", body),
+        ("dup", dup), ("drop", drop), ("swap", swap), ("over", over), ("rot", rot), ("nip", nip), ("tuck", tuck),
+        ("pick", pick), ("roll", roll),
+    ])?;
+    pack.dictionary.internal("rollback", rollback);
+    register_list(pack)?;
+    register_map(pack)?;
+    register_combinators(pack)?;
+    register_strings(pack)?;
+    register_format(pack)
 }
 
 fn open_parenth(pack: &mut Pack) -> Result<bool, Error> {
     pack.stack.start_stack();
+    pack.call_hooks(|hook, pack| hook.on_stack_open(pack));
+    Ok(true)
+}
+
+fn open_list(pack: &mut Pack) -> Result<bool, Error> {
+    pack.stack.start_list();
+    pack.call_hooks(|hook, pack| hook.on_stack_open(pack));
     Ok(true)
 }
 
 fn close_parenth(pack: &mut Pack) -> Result<bool, Error> {
-    if let None = pack.stack.end_stack() {
+    let list = if pack.stack.is_list() { Some(pack.stack.take()) } else { None };
+    if pack.stack.end_stack().is_none() {
         Err(Error::new("close_parenth: Stack level undeflow".into()))
     }
     else {
+        if let Some(list) = list {
+            pack.stack.push(list.into());
+        }
+        pack.call_hooks(|hook, pack| hook.on_stack_close(pack));
         Ok(true)
     }
 }
@@ -43,7 +72,11 @@ fn open_curly(pack: &mut Pack) -> Result<bool, Error> {
                     level -= 1;
                     if level == 0 {
                         let len = pack.concat.pointer - pos;
-                        pack.stack.push(BlockRef { pos, len }.into());
+                        let block = BlockRef { pos, len };
+                        // Cells without a scope get the one where the block is created, to resolve the words they use
+                        let scope = pack.dictionary.scope_index();
+                        pack.concat.set_scope(block, scope);
+                        pack.stack.push(block.into());
                         break;
                     }
                 }
@@ -59,7 +92,7 @@ fn open_curly(pack: &mut Pack) -> Result<bool, Error> {
     Ok(true)
 }
 
-fn close_curly(pack: &mut Pack) -> Result<bool, Error> {
+pub(crate) fn close_curly(pack: &mut Pack) -> Result<bool, Error> {
     if let Some(pos) = pack.ret.pop() {
         pack.concat.pointer = pos;
         Ok(true)
@@ -71,40 +104,29 @@ fn close_curly(pack: &mut Pack) -> Result<bool, Error> {
 
 fn lex(pack: &mut Pack) -> Result<bool, Error> {
     if let Some(Cell::Word(lex_name)) = pack.concat.next() {
-        if pack.dictionary.lex.is_empty() {
-            pack.dictionary.lex = lex_name.clone() + ".";
-        }
-        else {
-            pack.dictionary.lex = pack.dictionary.lex.clone() + &lex_name + ".";
-        }
+        let lex_name = lex_name.clone();
+        pack.dictionary.open_lex(&lex_name);
         Ok(true)
     }
     else {
-        return Err(Error::new("lex: couldn't find string".into()));
+        Err(Error::new("lex: couldn't find string".into()))
     }
 }
 
 fn close_lex(pack: &mut Pack) -> Result<bool, Error> {
-    let mut index = 0;
-    let mut cnt = 0;
-    for (i, c) in pack.dictionary.lex.char_indices().rev() {
-        if c == '.' {
-            index = i;
-            cnt += 1;
-            if cnt == 2 {
-                break;
-            }
-        }
-    }
+    pack.dictionary.close_lex();
+    Ok(true)
+}
 
-    if cnt < 2 {
-        pack.dictionary.lex = "".into();
+fn use_word(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Word(lex_name)) = pack.concat.next() {
+        let lex_name = lex_name.clone();
+        pack.dictionary.import(&lex_name);
+        Ok(true)
     }
     else {
-        pack.dictionary.lex.truncate(index + 1);
+        Err(Error::new("use: couldn't find a lexicon name".into()))
     }
-
-    Ok(true)
 }
 
 fn two_num_op(stack: &mut Stack, int_op: fn(i64, i64) -> i64, flt_op: fn(f64, f64) -> f64) -> Result<bool, Error> {
@@ -139,7 +161,7 @@ fn two_num_or_str_op(stack: &mut Stack, int_op: fn(i64, i64) -> i64, flt_op: fn(
 }
 
 fn plus(pack: &mut Pack) -> Result<bool, Error> {
-    two_num_or_str_op(&mut pack.stack, |a, b| a + b, |a, b| a + b, |a, b| a.clone() + &b)
+    two_num_or_str_op(&mut pack.stack, |a, b| a + b, |a, b| a + b, |a, b| a.clone() + b)
 }
 
 fn minus(pack: &mut Pack) -> Result<bool, Error> {
@@ -233,46 +255,34 @@ fn wipe(pack: &mut Pack) -> Result<bool, Error> {
     Ok(true)
 }
 
+/// Run a branch of `if`: words, closures and quotations are executed, other cells are pushed.
+fn if_branch(pack: &mut Pack, cell: Cell) -> Result<bool, Error> {
+    match cell {
+        Cell::Word(_) | Cell::Closure(_) | Cell::Quotation(_) => pack.jump(cell),
+        cell => {
+            pack.stack.push(cell);
+            Ok(true)
+        },
+    }
+}
+
 fn if_word(pack: &mut Pack) -> Result<bool, Error> {
     if let Some(Cell::Boolean(cond)) = pack.stack.pop() {
         if cond {
-            let true_word = pack.concat.next();
+            let true_word = pack.concat.next_clone();
             match true_word {
-                Some(Cell::Word(true_word)) => {
-                    if let Some(dict_entry) = pack.dictionary.dict.get(true_word) {
-                        let dict_entry = dict_entry.clone();
-                        pack.concat.next(); // discard the false condition word
-                        pack.exec_dict_entry(dict_entry)
-                    }
-                    else {
-                        Err(Error::new("if: couldn't find a word for true in the dictionary".into()))
-                    }
-                },
                 Some(cell) => {
-                    pack.stack.push(cell.clone());
                     pack.concat.next(); // discard the false condition word
-                    Ok(true)
+                    if_branch(pack, cell)
                 },
                 _ => Err(Error::new("if: couldn't find a word for true".into()))
             }
         }
         else {
             pack.concat.next(); // discard the true condition word
-            let false_word = pack.concat.next();
+            let false_word = pack.concat.next_clone();
             match false_word {
-                Some(Cell::Word(false_word)) => {
-                    if let Some(dict_entry) = pack.dictionary.dict.get(false_word) {
-                        let dict_entry = dict_entry.clone();
-                        pack.exec_dict_entry(dict_entry)
-                    }
-                    else {
-                        Err(Error::new("if: couldn't find a word for false in the dictionary".into()))
-                    }
-                },
-                Some(cell) => {
-                    pack.stack.push(cell.clone());
-                    Ok(true)
-                },
+                Some(cell) => if_branch(pack, cell),
                 _ => Err(Error::new("if: couldn't find a word for false".into()))
             }
         }
@@ -282,68 +292,231 @@ fn if_word(pack: &mut Pack) -> Result<bool, Error> {
     }
 }
 
-//TODO: Remove?
+/// Check if a cell can be executed: a block, a closure, a quotation or a word reference.
+fn is_callable(cell: &Cell) -> bool {
+    matches!(cell, Cell::Block(_) | Cell::Closure(_) | Cell::Quotation(_) | Cell::Word(_))
+}
+
 fn either(pack: &mut Pack) -> Result<bool, Error> {
-    if let (Some(Cell::Block(false_blk)), Some(Cell::Block(true_blk)), Some(Cell::Boolean(cond))) = (pack.stack.pop(), pack.stack.pop(), pack.stack.pop()) {
-        if cond {
-            pack.ret.push(pack.concat.pointer);
-            pack.concat.pointer = true_blk.pos;
+    match (pack.stack.pop(), pack.stack.pop(), pack.stack.pop()) {
+        (Some(false_blk), Some(true_blk), Some(Cell::Boolean(cond))) if is_callable(&false_blk) && is_callable(&true_blk) => {
+            pack.jump(if cond { true_blk } else { false_blk })
+        },
+        _ => Err(Error::new("either: couldn't find condition and 2 blocks".into())),
+    }
+}
+
+fn open_bracket(pack: &mut Pack) -> Result<bool, Error> {
+    // Stack transfers are compiled the first time, and then taken from the cache
+    let pos = pack.concat.pointer;
+    if !pack.concat.transfers.contains_key(&pos) {
+        let transfer = compile_transfer(&pack.concat.array, pos)?;
+        pack.concat.transfers.insert(pos, transfer);
+    }
+    let Pack { concat, stack, .. } = pack;
+    let transfer = &concat.transfers[&pos];
+    if stack.transfer(transfer.inputs, &transfer.outputs) {
+        concat.pointer += transfer.len;
+        Ok(true)
+    }
+    else {
+        Err(Error::new("open_bracket: stack is empty".into()))
+    }
+}
+
+fn compile_transfer(array: &[Cell], pos: usize) -> Result<Transfer, Error> {
+    let mut names: Vec<&String> = Vec::new();
+    let mut transfer = Transfer::default();
+    let mut taking = true;
+    for cell in array[pos..].iter() {
+        transfer.len += 1;
+        match cell {
+            Cell::Word(w) if w == "|" && taking => {
+                taking = false;
+                transfer.inputs = names.len();
+            },
+            Cell::Word(w) if w == "]" && !taking => return Ok(transfer),
+            Cell::Word(w) if taking => names.push(w),
+            Cell::Word(w) => {
+                // The first name takes the cell at the top, and a repeated name refers to the last cell taken with it
+                if let Some(index) = names.iter().rposition(|name| *name == w) {
+                    transfer.outputs.push(index);
+                }
+                else {
+                    return Err(Error::new("open_bracket: Couldn't find variable name".into()));
+                }
+            },
+            _ => return Err(Error::new("open_bracket: Expecting a variable name".into())),
+        }
+    }
+    Err(Error::new("open_bracket: Couldn't find the end of the stack transfer".into()))
+}
+
+fn shuffle(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Integer(code)) = pack.concat.next() {
+        // Compiled stack transfer: 4 bits for number of inputs, 4 for outputs, and 4 for each output index
+        let code = *code as u64;
+        let (inputs, outputs) = ((code & 0xF) as usize, ((code >> 4) & 0xF) as usize);
+        let mut indexes = [0; 14];
+        for (i, index) in indexes.iter_mut().enumerate().take(outputs) {
+            *index = ((code >> (8 + 4 * i)) & 0xF) as usize;
+        }
+        if pack.stack.transfer(inputs, &indexes[..outputs.min(14)]) {
+            Ok(true)
         }
         else {
-            pack.ret.push(pack.concat.pointer);
-            pack.concat.pointer = false_blk.pos;
+            Err(Error::new("shuffle: stack is empty".into()))
         }
+    }
+    else {
+        Err(Error::new("shuffle: Couldn't get an integer from the concat".into()))
+    }
+}
+
+fn dup(pack: &mut Pack) -> Result<bool, Error> {
+    stack_op(pack, "dup", 1, &[0, 0])
+}
+
+fn drop(pack: &mut Pack) -> Result<bool, Error> {
+    stack_op(pack, "drop", 1, &[])
+}
+
+fn swap(pack: &mut Pack) -> Result<bool, Error> {
+    stack_op(pack, "swap", 2, &[0, 1])
+}
+
+fn over(pack: &mut Pack) -> Result<bool, Error> {
+    stack_op(pack, "over", 2, &[1, 0, 1])
+}
+
+fn rot(pack: &mut Pack) -> Result<bool, Error> {
+    stack_op(pack, "rot", 3, &[1, 0, 2])
+}
+
+fn nip(pack: &mut Pack) -> Result<bool, Error> {
+    stack_op(pack, "nip", 2, &[0])
+}
+
+fn tuck(pack: &mut Pack) -> Result<bool, Error> {
+    stack_op(pack, "tuck", 2, &[0, 1, 0])
+}
+
+fn stack_op(pack: &mut Pack, name: &str, inputs: usize, outputs: &[usize]) -> Result<bool, Error> {
+    if pack.stack.transfer(inputs, outputs) {
         Ok(true)
     }
     else {
-        Err(Error::new("either: couldn't find condition and 2 blocks".into()))
+        Err(Error::new(format!("{}: stack is empty", name)))
     }
 }
 
-fn open_bracket(pack: &mut Pack) -> Result<bool, Error> {
-    let mut vars: HashMap<String, Cell> = HashMap::with_capacity(16);
-    while let Some(Cell::Word(w)) = pack.concat.next() {
-        if w == "|" {
-            break;
+fn pick(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Integer(n)) = pack.stack.pop() {
+        if let Some(cell) = pack.stack.get(n as usize) {
+            let cell = cell.clone();
+            pack.stack.push(cell);
+            return Ok(true);
         }
-        else {
-            if let Some(cell) = pack.stack.pop() {
-                vars.insert(w.clone(), cell);
-            }
-            else {
-                return Err(Error::new("open_bracket: stack is empty".into()));
-            }
+    }
+    Err(Error::new("pick: Couldn't get an integer and a cell at that position from the stack".into()))
+}
+
+fn roll(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Integer(n)) = pack.stack.pop() {
+        if let Some(cell) = pack.stack.remove(n as usize) {
+            pack.stack.push(cell);
+            return Ok(true);
         }
     }
-    while let Some(Cell::Word(w)) = pack.concat.next() {
-        if w == "]" {
-            break;
+    Err(Error::new("roll: Couldn't get an integer and a cell at that position from the stack".into()))
+}
+
+fn locals(pack: &mut Pack) -> Result<bool, Error> {
+    // Local definitions are compiled the first time, and then taken from the cache
+    let pos = pack.concat.pointer;
+    if !pack.concat.locals.contains_key(&pos) {
+        let locals = compile_locals(&pack.concat.array, pos)?;
+        pack.concat.locals.insert(pos, locals);
+    }
+    if pack.ret.size() == 0 {
+        return Err(Error::new("locals: Local variables can only be defined inside a block".into()));
+    }
+    let Locals { names, len, end } = pack.concat.locals[&pos].clone();
+    // Like in a stack transfer, the first name takes the cell at the top
+    let mut values = Vec::with_capacity(names.len());
+    for _ in 0..names.len() {
+        values.push(pack.stack.pop().ok_or_else(|| Error::new("locals: stack is empty".into()))?);
+    }
+    pack.ret.push_frame(Frame { depth: pack.ret.size(), start: pos - 1, end, names, values });
+    pack.concat.pointer += len;
+    Ok(true)
+}
+
+fn compile_locals(array: &[Cell], pos: usize) -> Result<Locals, Error> {
+    let mut names = Vec::new();
+    let mut len = 0;
+    loop {
+        len += 1;
+        match array.get(pos + len - 1) {
+            Some(Cell::Word(w)) if w == "|" => break,
+            Some(Cell::Word(w)) => names.push(w.clone()),
+            _ => return Err(Error::new("locals: Expecting a variable name or '|'".into())),
         }
-        else {
-            if let Some(k) = vars.get(w) {
-                pack.stack.push(k.clone());
-            }
-            else {
-                return Err(Error::new("open_bracket: Couldn't find variable name".into()));
-            }
+    }
+    let mut level = 0;
+    for (i, cell) in array[pos + len..].iter().enumerate() {
+        match cell {
+            Cell::Word(w) if w == "{" => level += 1,
+            Cell::Word(w) if w == "}" && level == 0 => {
+                return Ok(Locals { names: Rc::new(names), len, end: pos + len + i });
+            },
+            Cell::Word(w) if w == "}" => level -= 1,
+            _ => {},
         }
     }
-    Ok(true)
+    Err(Error::new("locals: Couldn't find the end of the block".into()))
+}
+
+fn closure(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Block(block)) = pack.stack.pop() {
+        let (names, values) = pack.ret.visible_locals(pack.concat.pointer.saturating_sub(1));
+        pack.stack.push(Closure { block, skip: 0, names: Rc::new(names), values }.into());
+        Ok(true)
+    }
+    else {
+        Err(Error::new("closure: Couldn't get a block from the stack".into()))
+    }
+}
+
+fn capture(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Block(block)) = pack.stack.pop() {
+        if !matches!(pack.concat.array.get(block.pos), Some(Cell::Word(w)) if w == "|") {
+            return Err(Error::new("capture: The block must start with a definition of local variables".into()));
+        }
+        let pos = block.pos + 1;
+        if !pack.concat.locals.contains_key(&pos) {
+            let locals = compile_locals(&pack.concat.array, pos)?;
+            pack.concat.locals.insert(pos, locals);
+        }
+        let Locals { names, len, .. } = pack.concat.locals[&pos].clone();
+        let mut values = Vec::with_capacity(names.len());
+        for _ in 0..names.len() {
+            values.push(pack.stack.pop().ok_or_else(|| Error::new("capture: stack is empty".into()))?);
+        }
+        pack.stack.push(Closure { block, skip: len + 1, names, values }.into());
+        Ok(true)
+    }
+    else {
+        Err(Error::new("capture: Couldn't get a block from the stack".into()))
+    }
 }
 
 fn exe(pack: &mut Pack) -> Result<bool, Error> {
-    match pack.stack.pop() {
-        Some(Cell::Block(blk)) => {
-            pack.ret.push(pack.concat.pointer);
-            pack.concat.pointer = blk.pos;
-            Ok(true)
-        },
-        Some(Cell::Word(w)) => pack.exec(&w),
-        Some(cell) => {
-            pack.stack.push(cell);
-            Ok(true)
-        },
-        _ => Err(Error::new("exe: Couldn't get a valid cell from the stack".into()))
+    if let Some(cell) = pack.stack.pop() {
+        pack.jump(cell)
+    }
+    else {
+        Err(Error::new("exe: Couldn't get a valid cell from the stack".into()))
     }
 }
 
@@ -397,6 +570,11 @@ fn type_word(pack: &mut Pack) -> Result<bool, Error> {
             Cell::Word(_) => "word",
             Cell::Block(_) => "block",
             Cell::Struct(_) => "struct",
+            Cell::Continuation(_) => "continuation",
+            Cell::Closure(_) => "closure",
+            Cell::Quotation(_) => "quotation",
+            Cell::List(_) => "list",
+            Cell::Map(_) => "map",
         };
         pack.stack.push(type_str.into());
         Ok(true)
@@ -408,12 +586,8 @@ fn type_word(pack: &mut Pack) -> Result<bool, Error> {
 
 fn question(pack: &mut Pack) -> Result<bool, Error> {
     if let (Some(Cell::Word(word)), Some(Cell::String(stack_effect)), Some(Cell::String(description))) = (pack.concat.next_clone(), pack.concat.next_clone(), pack.concat.next_clone()) {
-        if pack.dictionary.dict.contains_key("?__") {
-            let stack_help_word = format!("?_{word}_stack_");
-            let desc_help_word = format!("?_{word}_desc_");
-            pack.dictionary.data(&stack_help_word, stack_effect.into());
-            pack.dictionary.data(&desc_help_word, description.into());
-        }
+        let word = pack.dictionary.lex.clone() + &word;
+        pack.docs.set(&word, &stack_effect, &description);
         Ok(true)
     }
     else {
@@ -421,10 +595,33 @@ fn question(pack: &mut Pack) -> Result<bool, Error> {
     }
 }
 
+fn help(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Word(word)) = pack.concat.next_clone() {
+        // Words without documentation of their own may be resolved to a documented word of a lexicon
+        let resolved = match pack.docs.get(&word) {
+            Some(_) => None,
+            None => pack.resolve(&word, pack.concat.pointer - 2).map(|w| w.into_owned()),
+        };
+        if let Some(doc) = pack.docs.get(resolved.as_ref().unwrap_or(&word)) {
+            let (stack_effect, description) = (doc.stack.clone(), doc.desc.clone());
+            pack.stack.push(stack_effect.into());
+            pack.stack.push(description.into());
+            Ok(true)
+        }
+        else {
+            Err(Error::new(format!("help: Word '{}' is not documented", word)))
+        }
+    }
+    else {
+        Err(Error::new("help: Couldn't get a word from the concat".into()))
+    }
+}
+
 fn atat(pack: &mut Pack) -> Result<bool, Error>  {
-    if let Some(parent_concat_pos) = pack.ret.pop() {
+    if let Some(parent_concat_pos) = pack.ret.get(0) {
         if let Some(cell) = pack.concat.array.get(parent_concat_pos) {
-            pack.ret.push(parent_concat_pos + 1);
+            // Popping the return address would release the frame of the locals of the current block
+            pack.ret.replace_top(parent_concat_pos + 1);
             pack.stack.push(cell.clone());
             Ok(true)
         }
@@ -441,14 +638,15 @@ fn atdef(pack: &mut Pack) -> Result<bool, Error> {
     let (word, data) = (pack.stack.pop(), pack.stack.pop());
     if let Some(Cell::Word(word)) = word {
         if let Some(Cell::Block(block)) = data {
-            pack.dictionary.block(&word, block);
+            pack.dictionary.block(&word, block)?;
         }
         else if let Some(cell) = data {
-            pack.dictionary.data(&word, cell);
+            pack.dictionary.data(&word, cell)?;
         }
         else {
             return Err(Error::new("atdef: Expecting a block or a cell".into()));
         }
+        pack.call_hooks(|hook, pack| hook.on_define(pack, &word));
     }
     else {
         return Err(Error::new("atdef: Expecting a word in the stack".into()));
@@ -468,20 +666,13 @@ fn skip(pack: &mut Pack) -> Result<bool, Error> {
 
 fn block(pack: &mut Pack) -> Result<bool, Error> {
     if let Some(Cell::Block(block)) = pack.stack.pop() {
-        // New block will start at the end of current concat + 3 ("N skip {").
-        let new_block_pos = pack.concat.array.len() + 3;
-        let new_block_len = block.len;
-        // Add skip and {
-        pack.concat.array.push((new_block_len as i64 + 1).into());
-        pack.concat.array.push(Cell::Word("skip".into()));
-        pack.concat.array.push(Cell::Word("{".into()));
-        // Copy the block to the end of the concat
+        let mut cells = Vec::with_capacity(block.len);
         for n in block.pos..(block.pos + block.len) {
             // Substitute any $ word with the cell in the stack
             if let Cell::Word(w) = &pack.concat.array[n] {
                 if w == "$" {
                     if let Some(cell) = pack.stack.pop() {
-                        pack.concat.array.push(cell);
+                        cells.push(cell);
                         continue;
                     }
                     else {
@@ -489,10 +680,18 @@ fn block(pack: &mut Pack) -> Result<bool, Error> {
                     }
                 }
             }
-            pack.concat.array.push(pack.concat.array[n].clone());
+            cells.push(pack.concat.array[n].clone());
+        }
+        // Copy the block to the end of the concat and return the new block in the stack
+        let new_block = pack.append_block(cells);
+        // The copied cells keep the scope of the original ones
+        for i in 0..block.len {
+            let scope = pack.concat.scopes.get(block.pos + i).copied().unwrap_or(UNSCOPED);
+            if scope != UNSCOPED {
+                pack.concat.scopes[new_block.pos + i] = scope;
+            }
         }
-        // Return the new block in the stack
-        pack.stack.push(BlockRef { pos: new_block_pos, len: new_block_len }.into());
+        pack.stack.push(new_block.into());
         Ok(true)
     }
     else {
@@ -507,7 +706,11 @@ fn lex_sharp(pack: &mut Pack) -> Result<bool, Error> {
 
 fn exist_question(pack: &mut Pack) -> Result<bool, Error> {
     if let Some(Cell::Word(w)) = pack.stack.pop() {
-        let b = pack.dictionary.dict.contains_key(&w);
+        // Words forbidden by the sandbox don't exist for the script
+        let b = match pack.resolve(&w, pack.concat.pointer.saturating_sub(1)) {
+            Some(name) => pack.sandbox.permits(&name),
+            None => false,
+        };
         pack.stack.push(Cell::Word(w));
         pack.stack.push(b.into());
         Ok(true)
@@ -560,11 +763,13 @@ fn again(pack: &mut Pack) -> Result<bool, Error> {
 fn while_word(pack: &mut Pack) -> Result<bool, Error> {
     if pack.concat.array.len() > pack.concat.pointer + 2 {
         if let Cell::Word(condition) = &pack.concat.array[pack.concat.pointer] {
-            if let Some(DictEntry::Defined(condition)) = pack.dictionary.dict.get(condition) {
+            let condition = pack.resolve(condition, pack.concat.pointer).map(|w| w.into_owned());
+            if let Some(DictEntry::Defined(_)) = condition.as_ref().and_then(|w| pack.dictionary.dict.get(w)) {
+                let condition = condition.unwrap_or_default();
                 pack.ret.push(pack.concat.pointer - 1);
-                pack.ret.push(pack.concat.pointer + 1);
-                pack.concat.pointer = condition.pos;
-                return Ok(true);
+                // Executing the condition pushes the address of "do" into the return stack
+                pack.concat.pointer += 1;
+                return pack.exec(&condition);
             }
         }
     }
@@ -574,10 +779,18 @@ fn while_word(pack: &mut Pack) -> Result<bool, Error> {
 fn do_word(pack: &mut Pack) -> Result<bool, Error> {
     if pack.concat.array.len() > pack.concat.pointer {
         if let Cell::Word(action) = &pack.concat.array[pack.concat.pointer] {
-            if let Some(DictEntry::Defined(action)) = pack.dictionary.dict.get(action) {
+            let action = pack.resolve(action, pack.concat.pointer).map(|w| w.into_owned()).unwrap_or_default();
+            if let Some(DictEntry::Defined(action_block)) = pack.dictionary.dict.get(&action) {
                 if let Some(Cell::Boolean(condition)) = pack.stack.pop() {
                     if condition {
-                        pack.concat.pointer = action.pos;
+                        if !pack.sandbox.permits(&action) {
+                            return Err(Error::new(format!("do_word: Word '{}' is not allowed by the sandbox", action)));
+                        }
+                        // The action returns to the address of "while", already in the return stack
+                        let pos = action_block.pos;
+                        pack.call_hooks(|hook, pack| hook.before_word(pack, &action));
+                        pack.concat.pointer = pos;
+                        pack.call_hooks(|hook, pack| hook.after_word(pack, &action));
                     }
                     else {
                         pack.ret.pop(); // discard the address of "while"
@@ -589,4 +802,201 @@ fn do_word(pack: &mut Pack) -> Result<bool, Error> {
         }
     }
     Err(Error::new("do_word: it must have the structure 'while condition do action', with 'condition' and 'action' being defined word.".into()))
-}
\ No newline at end of file
+}
+
+fn callcc(pack: &mut Pack) -> Result<bool, Error> {
+    match pack.stack.pop() {
+        Some(cell) if is_callable(&cell) => {
+            pack.stack.push(pack.capture().into());
+            pack.jump(cell)
+        },
+        _ => Err(Error::new("callcc: Expecting a block, a closure, a quotation or a word reference".into()))
+    }
+}
+
+fn seal(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Word(w)) = pack.stack.pop() {
+        pack.dictionary.seal(&w);
+        Ok(true)
+    }
+    else {
+        Err(Error::new("seal: Couldn't get word ref from stack".into()))
+    }
+}
+
+fn forget(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Word(w)) = pack.concat.next_clone() {
+        let name = pack.dictionary.lex.clone() + &w;
+        pack.dictionary.forget(&name)?;
+        pack.docs.remove(&name);
+        Ok(true)
+    }
+    else {
+        Err(Error::new("forget: Couldn't get a word from the concat".into()))
+    }
+}
+
+fn private(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Word(w)) = pack.concat.next_clone() {
+        if pack.dictionary.lex.is_empty() {
+            return Err(Error::new("private: Words can only be private inside a lexicon".into()));
+        }
+        let name = pack.dictionary.lex.clone() + &w;
+        pack.dictionary.make_private(&name);
+        Ok(true)
+    }
+    else {
+        Err(Error::new("private: Couldn't get a word from the concat".into()))
+    }
+}
+
+fn require(pack: &mut Pack) -> Result<bool, Error> {
+    let name = if let Some(Cell::String(name)) = pack.concat.next_clone() {
+        name
+    }
+    else {
+        return Err(Error::new("require: Couldn't get a module name from the concat".into()));
+    };
+    if pack.modules.is_loaded(&name) {
+        return Ok(true);
+    }
+    if let Some(i) = pack.modules.loading.iter().position(|loading| loading.name == name) {
+        let mut cycle: Vec<&str> = pack.modules.loading[i..].iter().map(|loading| loading.name.as_str()).collect();
+        cycle.push(&name);
+        return Err(Error::new(format!("require: Cyclic dependency between modules: {}", cycle.join(" -> "))));
+    }
+    let source = match &pack.modules.resolver {
+        Some(resolver) => resolver.borrow_mut().resolve(&name)?,
+        None => return Err(Error::new("require: There is no module resolver".into())),
+    };
+    // The module runs in its own lexicon, without the imports of the caller. It's set before parsing the
+    // module code, so its cells get the scope of the module.
+    let lex = core::mem::replace(&mut pack.dictionary.lex, name.clone() + ".");
+    let imports = core::mem::take(&mut pack.dictionary.imports);
+    let depth = pack.dictionary.lex_depth();
+    // Append "N skip <module code> \require }" to the concat, and jump into the module code
+    let start = pack.concat.array.len();
+    pack.concat.array.push(Cell::Integer(0));
+    pack.concat.array.push(Cell::Word("skip".into()));
+    // The diagnostics are for the code appended by the host, not for the modules loaded at runtime
+    let _ = pack.code_named(&name, &source);
+    pack.concat.array.push(Cell::Word("\\require".into()));
+    pack.concat.array.push(Cell::Word("}".into()));
+    pack.concat.array[start] = Cell::Integer((pack.concat.array.len() - start - 2) as i64);
+    pack.modules.loading.push(Loading { name, lex, imports, depth });
+    pack.ret.push(pack.concat.pointer);
+    pack.concat.pointer = start + 2;
+    Ok(true)
+}
+
+fn close_require(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Loading { name, lex, imports, depth }) = pack.modules.loading.pop() {
+        pack.dictionary.restore_lex(lex, imports, depth);
+        pack.modules.loaded.insert(name);
+        Ok(true)
+    }
+    else {
+        Err(Error::new("\\require: No module is being loaded".into()))
+    }
+}
+
+fn marker(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Word(w)) = pack.concat.next_clone() {
+        let name = pack.dictionary.lex.clone() + &w;
+        // Create the marker before defining the word, so rolling back also removes the word
+        pack.dictionary.marker(&name);
+        // The internal rollback can't be redefined or shadowed
+        let rollback = format!("{}rollback", INTERNAL);
+        let block = pack.append_block(vec![name.into(), Cell::Word(rollback), Cell::Word("}".into())]);
+        pack.dictionary.block(&w, block)?;
+        Ok(true)
+    }
+    else {
+        Err(Error::new("marker: Couldn't get a word from the concat".into()))
+    }
+}
+
+fn rollback(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::String(name)) = pack.stack.pop() {
+        pack.dictionary.rollback(&name)?;
+        // The documentation of the removed words is removed too
+        let removed: Vec<String> = pack.docs.words().into_iter()
+            .filter(|word| !pack.dictionary.dict.contains_key(*word))
+            .cloned()
+            .collect();
+        removed.iter().for_each(|word| pack.docs.remove(word));
+        Ok(true)
+    }
+    else {
+        Err(Error::new("rollback: Couldn't get a marker name from the stack".into()))
+    }
+}
+
+fn words(pack: &mut Pack) -> Result<bool, Error> {
+    let words: Vec<String> = pack.dictionary.words().into_iter().cloned().collect();
+    words.into_iter().for_each(|w| pack.stack.push(Cell::Word(w)));
+    Ok(true)
+}
+
+fn lex_words(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::String(prefix)) = pack.stack.pop() {
+        let words: Vec<String> = pack.dictionary.lex_words(&prefix).into_iter().cloned().collect();
+        words.into_iter().for_each(|w| pack.stack.push(Cell::Word(w)));
+        Ok(true)
+    }
+    else {
+        Err(Error::new("lex_words: Couldn't get a string from the stack".into()))
+    }
+}
+
+/// Resolve the word `w` inspected by the native `word`, like `Pack::exec` does: it must be visible from the scope of
+/// the current cell and permitted by the sandbox. Returns the full name.
+fn resolve_inspected(pack: &Pack, word: &str, w: &str) -> Result<String, Error> {
+    let pos = pack.concat.pointer.saturating_sub(1);
+    let name = match pack.resolve(w, pos) {
+        Some(name) => name.into_owned(),
+        None if pack.dictionary.is_private(w) => {
+            return Err(Error::new(format!("{}: Word '{}' is private, it can only be used inside its lexicon", word, w)));
+        },
+        None => w.into(),
+    };
+    if pack.sandbox.permits(&name) {
+        Ok(name)
+    }
+    else {
+        Err(Error::new(format!("{}: Word '{}' is not allowed by the sandbox", word, name)))
+    }
+}
+
+fn kind(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Word(w)) = pack.stack.pop() {
+        let name = resolve_inspected(pack, "kind", &w)?;
+        if let Some(kind) = pack.dictionary.kind(&name) {
+            pack.stack.push(Cell::Word(w));
+            pack.stack.push(kind.into());
+            Ok(true)
+        }
+        else {
+            Err(Error::new(format!("kind: Word '{}' doesn't exist in dictionary", w)))
+        }
+    }
+    else {
+        Err(Error::new("kind: Couldn't get word ref from stack".into()))
+    }
+}
+
+fn body(pack: &mut Pack) -> Result<bool, Error> {
+    if let Some(Cell::Word(w)) = pack.stack.pop() {
+        let name = resolve_inspected(pack, "body", &w)?;
+        if let Some(block) = pack.dictionary.body(&name) {
+            pack.stack.push(block.into());
+            Ok(true)
+        }
+        else {
+            Err(Error::new(format!("body: Word '{}' is not a defined word", w)))
+        }
+    }
+    else {
+        Err(Error::new("body: Couldn't get word ref from stack".into()))
+    }
+}
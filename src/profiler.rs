@@ -0,0 +1,193 @@
+use super::core::{Pack, Hook, Cell, DictEntry, Error};
+use hashbrown::HashMap;
+use alloc::{vec::Vec, string::String};
+use core::fmt;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+#[derive(Default, Debug, Clone, PartialEq)]
+/// Profiling counters of a word.
+pub struct WordStats {
+    /// Number of times the word was executed.
+    pub calls: u64,
+    /// Steps executed by the word, including the words it called.
+    pub inclusive: u64,
+    /// Steps executed by the word itself.
+    pub exclusive: u64,
+    /// Wall time spent in the word, including the words it called.
+    #[cfg(feature = "std")]
+    pub time: Duration,
+}
+
+#[derive(Debug, Clone)]
+struct Frame {
+    word: String,
+    depth: usize,
+    #[cfg(feature = "std")]
+    start: Instant,
+}
+
+#[derive(Default, Debug, Clone)]
+/// Execution profiler. It's a Hook, so it starts profiling once registered with `Pack::add_hook`.
+pub struct Profiler {
+    stats: HashMap<String, WordStats>,
+    calls: HashMap<(String, String), u64>,
+    frames: Vec<Frame>,
+    /// Natives in progress, they nest when a native calls code, like the combinators.
+    #[cfg(feature = "std")]
+    natives: Vec<Frame>,
+}
+
+impl Profiler {
+    /// Create an empty profiler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clear all counters.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Counters of a word.
+    pub fn stats(&self, word: &str) -> Option<&WordStats> {
+        self.stats.get(word)
+    }
+
+    /// Counters of all executed words, sorted by exclusive steps, from the most expensive to the least.
+    pub fn report(&self) -> Vec<(&String, &WordStats)> {
+        let mut report: Vec<(&String, &WordStats)> = self.stats.iter().collect();
+        report.sort_by(|(word_a, a), (word_b, b)| {
+            b.exclusive.cmp(&a.exclusive).then(b.inclusive.cmp(&a.inclusive)).then(word_a.cmp(word_b))
+        });
+        report
+    }
+
+    /// Number of calls from each caller to each callee, sorted by number of calls.
+    /// An empty caller means the call was made from outside any defined word.
+    pub fn call_graph(&self) -> Vec<(&String, &String, u64)> {
+        let mut graph: Vec<(&String, &String, u64)> = self.calls.iter().map(|((caller, callee), n)| (caller, callee, *n)).collect();
+        graph.sort_by(|(caller_a, callee_a, a), (caller_b, callee_b, b)| {
+            b.cmp(a).then(caller_a.cmp(caller_b)).then(callee_a.cmp(callee_b))
+        });
+        graph
+    }
+
+    /// Close the frames of the words still running, and of the natives in progress. The return of the last word
+    /// executed can't be detected, because there isn't a step after it, so call it when the execution ends, before
+    /// reading the times. It's called on errors too.
+    pub fn finish(&mut self) {
+        self.close_frames(0);
+    }
+
+    /// Discard frames of the words that already returned, according to the RetStack.
+    fn unwind(&mut self, pack: &Pack) {
+        self.close_frames(pack.ret.size());
+    }
+
+    /// Discard frames deeper than `depth` in the RetStack, adding their time. Natives are closed when the code they
+    /// called escapes, with `leave` or a continuation.
+    fn close_frames(&mut self, depth: usize) {
+        #[cfg(feature = "std")]
+        while matches!(self.natives.last(), Some(native) if native.depth > depth) {
+            let native = self.natives.pop().unwrap();
+            self.add_native_time(native);
+        }
+        while let Some(frame) = self.frames.last() {
+            if frame.depth <= depth {
+                break;
+            }
+            let frame = self.frames.pop().unwrap();
+            #[cfg(feature = "std")]
+            if !self.frames.iter().any(|f| f.word == frame.word) {
+                self.stats.entry(frame.word).or_default().time += frame.start.elapsed();
+            }
+            #[cfg(not(feature = "std"))]
+            let _ = frame;
+        }
+    }
+
+    /// Add the time of a native that finished, unless it's still running in an outer call.
+    #[cfg(feature = "std")]
+    fn add_native_time(&mut self, native: Frame) {
+        if !self.natives.iter().any(|f| f.word == native.word) {
+            self.stats.entry(native.word).or_default().time += native.start.elapsed();
+        }
+    }
+}
+
+impl Hook for Profiler {
+    fn on_step(&mut self, pack: &Pack) {
+        self.unwind(pack);
+        // Natives and data words take one step, and it's their own.
+        let own_step = match pack.concat.array.get(pack.concat.pointer) {
+            Some(Cell::Word(w)) => match pack.resolve(w, pack.concat.pointer) {
+                Some(word) => !matches!(pack.dictionary.dict.get(&*word), Some(DictEntry::Defined(_)) | None),
+                None => false,
+            },
+            _ => false,
+        };
+        for (i, frame) in self.frames.iter().enumerate() {
+            // Count each word only once per step, in case of recursion
+            if self.frames[..i].iter().any(|f| f.word == frame.word) {
+                continue;
+            }
+            let stats = self.stats.entry(frame.word.clone()).or_default();
+            stats.inclusive += 1;
+            if !own_step && i == self.frames.len() - 1 {
+                stats.exclusive += 1;
+            }
+        }
+    }
+
+    fn before_word(&mut self, pack: &Pack, word: &str) {
+        self.unwind(pack);
+        let caller = self.frames.last().map(|f| f.word.clone()).unwrap_or_default();
+        *self.calls.entry((caller, word.into())).or_default() += 1;
+        let stats = self.stats.entry(word.into()).or_default();
+        stats.calls += 1;
+        if !matches!(pack.dictionary.dict.get(word), Some(DictEntry::Defined(_))) {
+            stats.inclusive += 1;
+            stats.exclusive += 1;
+            #[cfg(feature = "std")]
+            self.natives.push(Frame { word: word.into(), depth: pack.ret.size(), start: Instant::now() });
+        }
+    }
+
+    fn after_word(&mut self, pack: &Pack, word: &str) {
+        if let Some(DictEntry::Defined(_)) = pack.dictionary.dict.get(word) {
+            self.frames.push(Frame {
+                word: word.into(),
+                depth: pack.ret.size(),
+                #[cfg(feature = "std")]
+                start: Instant::now(),
+            });
+        }
+        else {
+            #[cfg(feature = "std")]
+            if let Some(native) = self.natives.pop() {
+                self.add_native_time(native);
+            }
+        }
+    }
+
+    fn on_error(&mut self, _pack: &Pack, _error: &Error) {
+        self.finish();
+    }
+}
+
+impl fmt::Display for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<24} {:>10} {:>12} {:>12}", "word", "calls", "inclusive", "exclusive")?;
+        #[cfg(feature = "std")]
+        write!(f, " {:>12}", "time")?;
+        writeln!(f)?;
+        for (word, stats) in self.report() {
+            write!(f, "{:<24} {:>10} {:>12} {:>12}", word, stats.calls, stats.inclusive, stats.exclusive)?;
+            #[cfg(feature = "std")]
+            write!(f, " {:>12?}", stats.time)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
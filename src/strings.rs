@@ -0,0 +1,189 @@
+use super::core::{Pack, Cell, Error};
+use alloc::{string::String, vec::Vec};
+
+pub fn register_strings(pack: &mut Pack) -> Result<(), Error> {
+    pack.def_natives(&[
+        ("str.len", len), ("str.bytes", bytes), ("str.slice", slice), ("str.find", find), ("str.replace", replace),
+        ("str.split", split), ("str.join", join), ("str.trim", trim), ("str.upper", upper), ("str.lower", lower),
+        ("str.starts_with?", starts_with), ("str.ends_with?", ends_with), ("str.repeat", repeat),
+        ("str.char_at", char_at), ("str.code", code), ("str.from_code", from_code),
+    ])
+}
+
+fn pop_str(pack: &mut Pack, word: &str) -> Result<String, Error> {
+    if let Some(Cell::String(s)) = pack.stack.pop() {
+        Ok(s)
+    }
+    else {
+        Err(Error::new(format!("{}: Couldn't get a string from the stack", word)))
+    }
+}
+
+fn pop_int(pack: &mut Pack, word: &str) -> Result<i64, Error> {
+    if let Some(Cell::Integer(n)) = pack.stack.pop() {
+        Ok(n)
+    }
+    else {
+        Err(Error::new(format!("{}: Couldn't get an integer from the stack", word)))
+    }
+}
+
+/// Byte offset of char index `index` in `s`, that can be the end of the string.
+fn byte_offset(s: &str, index: i64) -> Option<usize> {
+    let index = usize::try_from(index).ok()?;
+    s.char_indices().map(|(offset, _)| offset).chain([s.len()]).nth(index)
+}
+
+/// Char index of byte offset `offset` in `s`.
+fn char_index(s: &str, offset: usize) -> i64 {
+    s[..offset].chars().count() as i64
+}
+
+fn len(pack: &mut Pack) -> Result<bool, Error> {
+    let s = pop_str(pack, "str.len")?;
+    pack.stack.push((s.chars().count() as i64).into());
+    Ok(true)
+}
+
+fn bytes(pack: &mut Pack) -> Result<bool, Error> {
+    let s = pop_str(pack, "str.bytes")?;
+    pack.stack.push((s.len() as i64).into());
+    Ok(true)
+}
+
+fn slice(pack: &mut Pack) -> Result<bool, Error> {
+    let end = pop_int(pack, "str.slice")?;
+    let start = pop_int(pack, "str.slice")?;
+    let s = pop_str(pack, "str.slice")?;
+    match (byte_offset(&s, start), byte_offset(&s, end)) {
+        (Some(a), Some(b)) if a <= b => {
+            pack.stack.push(s[a..b].into());
+            Ok(true)
+        },
+        _ => Err(Error::new(format!("str.slice: Range {}..{} out of bounds", start, end))),
+    }
+}
+
+fn find(pack: &mut Pack) -> Result<bool, Error> {
+    let pattern = pop_str(pack, "str.find")?;
+    let s = pop_str(pack, "str.find")?;
+    let index = s.find(&pattern).map(|offset| char_index(&s, offset)).unwrap_or(-1);
+    pack.stack.push(index.into());
+    Ok(true)
+}
+
+fn replace(pack: &mut Pack) -> Result<bool, Error> {
+    let to = pop_str(pack, "str.replace")?;
+    let from = pop_str(pack, "str.replace")?;
+    let s = pop_str(pack, "str.replace")?;
+    if from.is_empty() {
+        return Err(Error::new("str.replace: The pattern is empty".into()));
+    }
+    pack.stack.push(s.replace(&from, &to).into());
+    Ok(true)
+}
+
+fn split(pack: &mut Pack) -> Result<bool, Error> {
+    let sep = pop_str(pack, "str.split")?;
+    let s = pop_str(pack, "str.split")?;
+    let parts: Vec<Cell> = if sep.is_empty() {
+        s.chars().map(|c| Cell::String(c.into())).collect()
+    }
+    else {
+        s.split(sep.as_str()).map(Cell::from).collect()
+    };
+    // Always a list, even inside a nested stack: the blocks of combinators like "map" run in nested stacks too, and
+    // the result must not depend on that. The parts can be put in the stack with "spread".
+    pack.stack.push(parts.into());
+    Ok(true)
+}
+
+fn join(pack: &mut Pack) -> Result<bool, Error> {
+    let sep = pop_str(pack, "str.join")?;
+    // Join a list, or all the cells of the current stack
+    let cells = match pack.stack.get(0) {
+        Some(Cell::List(_)) => match pack.stack.pop() {
+            Some(Cell::List(cells)) => cells,
+            _ => Vec::new(),
+        },
+        _ => pack.stack.take(),
+    };
+    let mut out = String::new();
+    for (i, cell) in cells.into_iter().enumerate() {
+        if let Cell::String(s) = cell {
+            if i > 0 {
+                out.push_str(&sep);
+            }
+            out.push_str(&s);
+        }
+        else {
+            return Err(Error::new("str.join: All cells must be strings".into()));
+        }
+    }
+    pack.stack.push(out.into());
+    Ok(true)
+}
+
+fn trim(pack: &mut Pack) -> Result<bool, Error> {
+    let s = pop_str(pack, "str.trim")?;
+    pack.stack.push(s.trim().into());
+    Ok(true)
+}
+
+fn upper(pack: &mut Pack) -> Result<bool, Error> {
+    let s = pop_str(pack, "str.upper")?;
+    pack.stack.push(s.to_uppercase().into());
+    Ok(true)
+}
+
+fn lower(pack: &mut Pack) -> Result<bool, Error> {
+    let s = pop_str(pack, "str.lower")?;
+    pack.stack.push(s.to_lowercase().into());
+    Ok(true)
+}
+
+fn starts_with(pack: &mut Pack) -> Result<bool, Error> {
+    let prefix = pop_str(pack, "str.starts_with?")?;
+    let s = pop_str(pack, "str.starts_with?")?;
+    pack.stack.push(s.starts_with(&prefix).into());
+    Ok(true)
+}
+
+fn ends_with(pack: &mut Pack) -> Result<bool, Error> {
+    let suffix = pop_str(pack, "str.ends_with?")?;
+    let s = pop_str(pack, "str.ends_with?")?;
+    pack.stack.push(s.ends_with(&suffix).into());
+    Ok(true)
+}
+
+fn repeat(pack: &mut Pack) -> Result<bool, Error> {
+    let n = pop_int(pack, "str.repeat")?;
+    let s = pop_str(pack, "str.repeat")?;
+    let n = usize::try_from(n).map_err(|_| Error::new(format!("str.repeat: Invalid count {}", n)))?;
+    pack.stack.push(s.repeat(n).into());
+    Ok(true)
+}
+
+fn char_at(pack: &mut Pack) -> Result<bool, Error> {
+    let index = pop_int(pack, "str.char_at")?;
+    let s = pop_str(pack, "str.char_at")?;
+    let c = usize::try_from(index).ok().and_then(|index| s.chars().nth(index))
+        .ok_or_else(|| Error::new(format!("str.char_at: Index {} out of range", index)))?;
+    pack.stack.push(Cell::String(c.into()));
+    Ok(true)
+}
+
+fn code(pack: &mut Pack) -> Result<bool, Error> {
+    let s = pop_str(pack, "str.code")?;
+    let c = s.chars().next().ok_or_else(|| Error::new("str.code: The string is empty".into()))?;
+    pack.stack.push((c as i64).into());
+    Ok(true)
+}
+
+fn from_code(pack: &mut Pack) -> Result<bool, Error> {
+    let n = pop_int(pack, "str.from_code")?;
+    let c = u32::try_from(n).ok().and_then(char::from_u32)
+        .ok_or_else(|| Error::new(format!("str.from_code: Invalid char code {}", n)))?;
+    pack.stack.push(Cell::String(c.into()));
+    Ok(true)
+}
@@ -0,0 +1,32 @@
+use runpack::{Pack, Cell};
+
+/// Run the code and return the stack, or the error message.
+fn run(code: &str) -> Result<Vec<Cell>, String> {
+    let mut pack = Pack::new();
+    let _ = pack.code(code);
+    pack.run().map_err(|e| e.msg)?;
+    Ok(pack.stack.take())
+}
+
+#[test]
+fn leave_escapes_sequence_combinators() {
+    assert_eq!(run("{ #( 1 2 3 ) { 1 leave } map 'no' } def f f 'after'"), Ok(vec![Cell::Integer(1), "after".into()]));
+    assert_eq!(run("{ #( 3 1 2 ) { 1 leave } sort_by 'no' } def f f 'after'"), Ok(vec![Cell::Integer(3), "after".into()]));
+    assert_eq!(run("{ #( 1 2 3 ) { dup 2 = { 2 leave } { } either } each 'no' } def f f"), Ok(vec![Cell::Integer(1), Cell::Integer(2)]));
+    // Leaving only the block executed by the combinator is a normal return
+    assert_eq!(run("{ #( 1 2 3 ) { 0 leave } map } def f f"), Ok(vec![vec![Cell::Integer(1), Cell::Integer(2), Cell::Integer(3)].into()]));
+}
+
+#[test]
+fn continuations_escape_sequence_combinators() {
+    let code = "{ | k | #( 1 2 3 ) { | x | x 2 = { x k exe } { x } either } map 'no' } callcc 'after'";
+    assert_eq!(run(code), Ok(vec![Cell::Integer(2), "after".into()]));
+    let code = "{ | k | #( 1 2 3 ) { dup 2 = { k exe } { drop false } either } filter 'no' } callcc 'after'";
+    assert_eq!(run(code), Ok(vec![Cell::Integer(2), "after".into()]));
+}
+
+#[test]
+fn blocks_run_in_nested_stack() {
+    assert_eq!(run("5 #( 1 2 ) { 2 * } map"), Ok(vec![Cell::Integer(5), vec![Cell::Integer(2), Cell::Integer(4)].into()]));
+    assert_eq!(run("#( 1 2 ) { drop drop } map").unwrap_err(), "drop: stack is empty");
+}
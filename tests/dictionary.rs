@@ -0,0 +1,39 @@
+use runpack::{Pack, Cell};
+
+/// Run the code and return the stack, or the error message.
+fn run(code: &str) -> Result<Vec<Cell>, String> {
+    let mut pack = Pack::new();
+    let _ = pack.code(code);
+    pack.run().map_err(|e| e.msg)?;
+    Ok(pack.stack.take())
+}
+
+#[test]
+fn forget_and_rollback_clear_private() {
+    assert_eq!(run("lex m { 1 } def h private h \\lex m.h").unwrap_err(), "Word 'm.h' is private, it can only be used inside its lexicon");
+    assert_eq!(run("lex m { 1 } def h private h \\lex forget m.h 5 def m.h m.h"), Ok(vec![Cell::Integer(5)]));
+    assert_eq!(run("marker mk lex m { 1 } def h private h \\lex mk 5 def m.h m.h"), Ok(vec![Cell::Integer(5)]));
+    // Private words defined before the marker stay private
+    let code = "lex m { 1 } def h private h \\lex marker mk lex m { 2 } def g private g \\lex mk m.h";
+    assert_eq!(run(code).unwrap_err(), "Word 'm.h' is private, it can only be used inside its lexicon");
+}
+
+#[test]
+fn forget_uses_lexicon() {
+    assert_eq!(run("lex m { } def h forget h \\lex m.h").unwrap_err(), "Word 'm.h' doesn't exist in dictionary");
+    assert_eq!(run("{ 1 } def h lex m { 2 } def h forget h \\lex h"), Ok(vec![Cell::Integer(1)]));
+}
+
+#[test]
+fn natives_executed_before_any_cell() {
+    let mut pack = Pack::new();
+    pack.concat.pointer = 0;
+    pack.stack.push(Cell::Word("dup".into()));
+    pack.exec("exist?").unwrap();
+    assert_eq!(pack.stack.take(), vec![Cell::Word("dup".into()), true.into()]);
+    let _ = pack.code("{ 1 }");
+    pack.run().unwrap();
+    pack.concat.pointer = 0;
+    pack.exec("closure").unwrap();
+    assert!(matches!(pack.stack.take()[..], [Cell::Closure(_)]));
+}
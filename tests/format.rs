@@ -0,0 +1,21 @@
+use runpack::{Pack, Cell};
+
+/// Run the code and return the stack, or the error message.
+fn run(code: &str) -> Result<Vec<Cell>, String> {
+    let mut pack = Pack::new();
+    let _ = pack.code(code);
+    pack.run().map_err(|e| e.msg)?;
+    Ok(pack.stack.take())
+}
+
+#[test]
+fn fmt_limits_precision() {
+    assert_eq!(run("1.5 '{:.3}' fmt"), Ok(vec!["1.500".into()]));
+    assert_eq!(run("1.5 '{:.100}' fmt").unwrap()[0], Cell::String(format!("1.5{}", "0".repeat(99))));
+    assert_eq!(run("1.5 '{:.999999999}' fmt"), Err("fmt: Precision 999999999 is too big, the maximum is 100".into()));
+    // The cells are kept when the template is invalid
+    let mut pack = Pack::new();
+    let _ = pack.code("1.5 '{:.101}' fmt");
+    assert!(pack.run().is_err());
+    assert_eq!(pack.stack.take(), vec![Cell::Float(1.5), "{:.101}".into()]);
+}
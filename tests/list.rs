@@ -0,0 +1,68 @@
+use runpack::{Pack, Cell};
+
+/// Run the code and return the stack, or the error message.
+fn run(code: &str) -> Result<Vec<Cell>, String> {
+    let mut pack = Pack::new();
+    let _ = pack.code(code);
+    pack.run().map_err(|e| e.msg)?;
+    Ok(pack.stack.take())
+}
+
+fn list(values: &[i64]) -> Cell {
+    values.iter().map(|i| Cell::Integer(*i)).collect::<Vec<Cell>>().into()
+}
+
+fn is_true(code: &str) -> bool {
+    run(code) == Ok(vec![true.into()])
+}
+
+#[test]
+fn structural_equality() {
+    assert!(is_true("#( 1 2 3 ) #( 1 2 3 ) ="));
+    assert!(is_true("#( 1 #( 2 'a' ) ) #( 1 #( 2 'a' ) ) ="));
+    assert!(!is_true("#( 1 2 3 ) #( 1 2 ) ="));
+    // Cells of different types are never equal
+    assert!(!is_true("#( 1 2 ) #( 1 2.0 ) ="));
+}
+
+#[test]
+fn lexicographic_ordering() {
+    assert!(is_true("#( 1 2 ) #( 1 3 ) <"));
+    assert!(is_true("#( 2 ) #( 1 5 ) >"));
+    // A prefix goes first
+    assert!(is_true("#( 1 2 ) #( 1 2 3 ) <"));
+    assert!(is_true("#( ) #( 1 ) <"));
+    // Cells of different types are ordered by type, integers go before strings
+    assert!(is_true("#( 1 'a' ) #( 1 2 ) >"));
+    // A NaN makes the lists unordered
+    assert!(!is_true("#( 'NaN' >float ) #( 1.0 ) <"));
+    assert!(!is_true("#( 'NaN' >float ) #( 1.0 ) >="));
+    assert_eq!(run("#( #( 2 1 ) #( 1 2 3 ) #( 1 2 ) ) { } sort_by"), Ok(vec![vec![list(&[1, 2]), list(&[1, 2, 3]), list(&[2, 1])].into()]));
+}
+
+#[test]
+fn slicing() {
+    assert_eq!(run("#( 1 2 3 ) 1 3 list.slice"), Ok(vec![list(&[2, 3])]));
+    assert_eq!(run("#( 1 2 3 ) 0 0 list.slice"), Ok(vec![list(&[])]));
+    assert_eq!(run("#( 1 2 3 ) 2 1 list.slice").unwrap_err(), "list.slice: Range 2..1 out of bounds");
+    assert_eq!(run("#( 1 2 3 ) 1 4 list.slice").unwrap_err(), "list.slice: Range 1..4 out of bounds");
+    assert_eq!(run("#( 1 2 3 ) -1 2 list.slice").unwrap_err(), "list.slice: Range -1..2 out of bounds");
+}
+
+#[test]
+fn indexing() {
+    assert_eq!(run("#( 1 2 3 ) 0 10 list.set 2 list.get"), Ok(vec![Cell::Integer(3)]));
+    assert_eq!(run("#( 1 2 3 ) 3 list.get").unwrap_err(), "list.get: Index 3 out of range");
+    assert_eq!(run("#( 1 2 3 ) -1 list.get").unwrap_err(), "list.get: Index -1 out of range");
+    assert_eq!(run("#( 1 2 3 ) 3 10 list.set").unwrap_err(), "list.set: Index 3 out of range");
+    assert_eq!(run("#( 1 2 3 ) 2 list.index_of #( 1 2 3 ) 5 list.index_of"), Ok(vec![Cell::Integer(1), Cell::Integer(-1)]));
+}
+
+#[test]
+fn nested_stacks() {
+    assert_eq!(run("( 1 2 3 list )"), Ok(vec![list(&[1, 2, 3])]));
+    assert_eq!(run("#( 1 2 3 ) spread"), run("1 2 3"));
+    assert_eq!(run("#( 1 2 ) 3 list.push list.pop"), Ok(vec![list(&[1, 2]), Cell::Integer(3)]));
+    assert_eq!(run("#( ) list.pop").unwrap_err(), "list.pop: List is empty");
+    assert_eq!(run("#( 1 2 ) #( 3 ) list.concat list.reverse"), Ok(vec![list(&[3, 2, 1])]));
+}
@@ -0,0 +1,62 @@
+use runpack::{Pack, Cell};
+
+/// Run the code and return the stack, or the error message.
+fn run(code: &str) -> Result<Vec<Cell>, String> {
+    let mut pack = Pack::new();
+    let _ = pack.code(code);
+    pack.run().map_err(|e| e.msg)?;
+    Ok(pack.stack.take())
+}
+
+fn ints(values: &[i64]) -> Vec<Cell> {
+    values.iter().map(|i| Cell::Integer(*i)).collect()
+}
+
+#[test]
+fn binds_from_stack() {
+    // The first name takes the cell at the top
+    assert_eq!(run("{ | a b | a b } def f 1 2 f"), Ok(ints(&[2, 1])));
+    assert_eq!(run("{ | b a | a b - a! a a * } def diff_square 5 2 diff_square"), Ok(ints(&[9])));
+    assert_eq!(run("{ | a | } def f f").unwrap_err(), "locals: stack is empty");
+}
+
+#[test]
+fn works_with_recursion() {
+    let code = "{ | n | n 1 <= { 1 } { n 1 - factorial n * } either } def factorial 5 factorial";
+    assert_eq!(run(code), Ok(ints(&[120])));
+    assert_eq!(run("{ | a | a } def f { | a | 5 f a } def g 1 g"), Ok(ints(&[5, 1])));
+}
+
+#[test]
+fn scoped_to_block() {
+    assert_eq!(run("{ | a | a } def f 1 f a").unwrap_err(), "Word 'a' doesn't exist in dictionary");
+    // Nested blocks see the locals of the blocks around them, and can assign them
+    assert_eq!(run("{ | a | { a 1 + a! } exe a } def f 1 f"), Ok(ints(&[2])));
+    // Without a closure, a block returned by the word can't see the released locals
+    assert_eq!(run("{ | n | { n + } } def adder 5 10 adder exe").unwrap_err(), "Word 'n' doesn't exist in dictionary");
+    assert_eq!(run("{ | n | { n + } closure } def adder 5 10 adder exe"), Ok(ints(&[15])));
+}
+
+#[test]
+fn shadowing() {
+    // Inner locals shadow outer locals and dictionary words with the same name
+    assert_eq!(run("{ | a | { | a | a 10 * } exe a } def f 1 2 f"), Ok(ints(&[10, 2])));
+    assert_eq!(run("{ 1 } def a { | a | a } def f 5 f"), Ok(ints(&[5])));
+    assert_eq!(run("{ | x | x } def x 7 x"), Ok(ints(&[7])));
+    // "@@" takes the next cell of the caller, not of the locals frame
+    assert_eq!(run("{ | a | @@ a } def nc 1 nc 5"), Ok(ints(&[5, 1])));
+}
+
+#[test]
+fn released_on_leave() {
+    assert_eq!(run("{ | a | a 1 = { 1 leave } { } either 'no' } def f { 1 f 'after' } def g g"), Ok(vec!["after".into()]));
+    assert_eq!(run("{ | a | 0 leave } def f { 1 f a } def g g").unwrap_err(), "Word 'a' doesn't exist in dictionary");
+    let code = "{ | a | loop a 1 + a! a 3 = { a 2 leave } { } either true again } def f 0 f";
+    assert_eq!(run(code), Ok(ints(&[3])));
+}
+
+#[test]
+fn closures_keep_captured_values() {
+    assert_eq!(run("{ | n | { n 1 + n! n } closure } def counter 1 counter dup exe swap exe"), Ok(ints(&[2, 2])));
+    assert_eq!(run("10 { | n | n + } capture var add_ten 5 add_ten exe"), Ok(ints(&[15])));
+}
@@ -0,0 +1,15 @@
+#![cfg(feature = "std")]
+
+use runpack::{FileResolver, ModuleResolver};
+
+#[test]
+fn file_resolver_rejects_paths() {
+    let root = std::env::temp_dir().join("runpack_modules_test");
+    std::fs::create_dir_all(root.join("net")).unwrap();
+    std::fs::write(root.join("net").join("http.rp"), "{ 80 } def port").unwrap();
+    let mut resolver = FileResolver::new(&root);
+    assert_eq!(resolver.resolve("net.http").unwrap(), "{ 80 } def port");
+    for name in ["", "net.", ".net", "net..http", "/etc/passwd", "net/http", "net\\http"] {
+        assert_eq!(resolver.resolve(name).unwrap_err().msg, format!("require: Invalid module name '{}'", name));
+    }
+}
@@ -0,0 +1,120 @@
+use runpack::{Pack, Cell, Hook, Debugger, Pause, optimize, optimize_word};
+use std::{rc::Rc, cell::RefCell};
+
+/// Run the definitions, optimize them, and run the code. Returns the stack, or the error message.
+fn run(defs: &str, code: &str, optimized: bool) -> Result<Vec<Cell>, String> {
+    let mut pack = Pack::new();
+    let _ = pack.code(defs);
+    pack.run().map_err(|e| e.msg)?;
+    if optimized {
+        optimize(&mut pack);
+    }
+    let _ = pack.code(code);
+    pack.run().map_err(|e| e.msg)?;
+    Ok(pack.stack.take())
+}
+
+fn same_behavior(defs: &str, code: &str) {
+    assert_eq!(run(defs, code, false), run(defs, code, true), "defs: {}, code: {}", defs, code);
+}
+
+#[test]
+fn preserves_results() {
+    same_behavior("{ dup * } def sq { sq sq } def quad", "3 quad");
+    same_behavior("{ 2 3 + * } def five*", "4 five* 1.5 five*");
+    same_behavior("{ [ a b | b a a ] } def swap-dup", "1 2 swap-dup");
+    same_behavior("{ 'a' 'b' + + } def ab+", "'x' ab+");
+    same_behavior("{ { 1 } { 2 } if } def pick { true pick false pick } def both", "both");
+    same_behavior("{ 1 0 / } def bad", "bad");
+    same_behavior("{ is_int? } def int? { int? not } def not-int?", "1 not-int? 'a' not-int?");
+}
+
+#[test]
+fn optimizes_small_words() {
+    let mut pack = Pack::new();
+    let _ = pack.code("{ dup * } def sq { sq 1 2 + } def f");
+    pack.run().unwrap();
+    assert!(optimize_word(&mut pack, "f"));
+    assert_eq!(pack.dictionary.optimized("f").unwrap().deps, vec![String::from("sq"), String::from("+")]);
+}
+
+#[test]
+fn redefinition_restores_original() {
+    let mut pack = Pack::new();
+    let _ = pack.code("{ 1 } def one { one one + } def two");
+    pack.run().unwrap();
+    assert!(optimize_word(&mut pack, "two"));
+    let _ = pack.code("{ 10 } def one two");
+    pack.run().unwrap();
+    assert_eq!(pack.stack.take(), vec![Cell::Integer(20)]);
+    assert!(pack.dictionary.optimized("two").is_none());
+}
+
+#[test]
+fn sandbox_checks_inlined_words() {
+    let mut pack = Pack::new();
+    let _ = pack.code("{ 1 } def secret { secret } def reveal");
+    pack.run().unwrap();
+    assert!(optimize_word(&mut pack, "reveal"));
+    pack.sandbox.deny("secret");
+    let _ = pack.code("reveal");
+    assert_eq!(pack.run().unwrap_err().msg, "Word 'secret' is not allowed by the sandbox");
+}
+
+#[derive(Default)]
+struct WordLog(Vec<String>);
+
+impl Hook for WordLog {
+    fn before_word(&mut self, _pack: &Pack, word: &str) {
+        self.0.push(word.into());
+    }
+}
+
+#[test]
+fn hooks_see_inlined_words() {
+    let mut pack = Pack::new();
+    let _ = pack.code("{ 1 } def one { one } def f");
+    pack.run().unwrap();
+    assert!(optimize_word(&mut pack, "f"));
+    let log = Rc::new(RefCell::new(WordLog::default()));
+    pack.add_hook(log.clone());
+    let _ = pack.code("f");
+    pack.run().unwrap();
+    let words: Vec<String> = log.borrow().0.iter().filter(|w| *w == "f" || *w == "one").cloned().collect();
+    assert_eq!(words, vec!["f", "one"]);
+}
+
+#[test]
+fn debugger_breaks_on_inlined_words() {
+    let mut pack = Pack::new();
+    let _ = pack.code("{ 1 } def one { one } def f");
+    pack.run().unwrap();
+    assert!(optimize_word(&mut pack, "f"));
+    let one = pack.dictionary.body("one").unwrap().pos;
+    let _ = pack.code("f");
+    let mut debugger = Debugger::new();
+    debugger.break_word("one");
+    assert_eq!(debugger.cont(&mut pack).unwrap(), Pause::Breakpoint(one));
+}
+
+#[test]
+fn rollback_restores_original() {
+    let mut pack = Pack::new();
+    let _ = pack.code("{ 1 } def one { one } def f marker m");
+    pack.run().unwrap();
+    let original = pack.dictionary.body("f");
+    assert!(optimize_word(&mut pack, "f"));
+    let _ = pack.code("m");
+    pack.run().unwrap();
+    assert_eq!(pack.dictionary.body("f"), original);
+    assert!(pack.dictionary.optimized("f").is_none());
+}
+
+#[test]
+fn sealed_words_not_optimized() {
+    let mut pack = Pack::new();
+    let _ = pack.code("{ 1 } def one { one } def f @ f seal");
+    pack.run().unwrap();
+    assert!(!optimize_word(&mut pack, "f"));
+    assert!(pack.dictionary.optimized("f").is_none());
+}
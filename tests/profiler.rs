@@ -0,0 +1,52 @@
+use runpack::{Pack, Profiler, Error};
+use std::{rc::Rc, cell::RefCell};
+
+/// Run the code with a profiler registered, and finish it.
+fn profile(defs: &str, code: &str) -> Profiler {
+    let mut pack = Pack::new();
+    pack.dictionary.native("wait", wait).unwrap();
+    let _ = pack.code(defs);
+    pack.run().unwrap();
+    let profiler = Rc::new(RefCell::new(Profiler::new()));
+    pack.add_hook(profiler.clone());
+    let _ = pack.code(code);
+    pack.run().unwrap();
+    profiler.borrow_mut().finish();
+    let profiler = profiler.borrow().clone();
+    profiler
+}
+
+fn wait(_pack: &mut Pack) -> Result<bool, Error> {
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    Ok(true)
+}
+
+#[test]
+fn counts_nested_calls() {
+    let profiler = profile("{ 1 2 + drop } def inner { inner inner } def outer", "outer");
+    let outer = profiler.stats("outer").unwrap();
+    let inner = profiler.stats("inner").unwrap();
+    assert_eq!((outer.calls, inner.calls, profiler.stats("+").unwrap().calls), (1, 2, 2));
+    // Each "inner" runs 1, 2, +, drop and }
+    assert_eq!((inner.inclusive, inner.exclusive), (10, 4));
+    // Plus the two calls to "inner" and the final }
+    assert_eq!((outer.inclusive, outer.exclusive), (13, 2));
+    let graph: Vec<(String, String, u64)> = profiler.call_graph().into_iter().map(|(a, b, n)| (a.clone(), b.clone(), n)).collect();
+    assert!(graph.contains(&("outer".into(), "inner".into(), 2)));
+    assert!(graph.contains(&("".into(), "outer".into(), 1)));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn attributes_time_to_nested_calls() {
+    use std::time::Duration;
+    let profiler = profile("{ wait } def inner { inner wait } def outer", "outer");
+    let time = |word| profiler.stats(word).unwrap().time;
+    assert!(time("inner") >= Duration::from_millis(20));
+    assert!(time("wait") >= Duration::from_millis(40));
+    // The last word executed is closed by finish
+    assert!(time("outer") >= time("inner") + Duration::from_millis(20));
+    // Natives running inside a combinator don't reset its time
+    let profiler = profile("", "#( 1 2 ) { drop wait } each");
+    assert!(profiler.stats("each").unwrap().time >= Duration::from_millis(40));
+}
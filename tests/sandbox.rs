@@ -0,0 +1,68 @@
+use runpack::{Pack, Cell};
+
+/// Run the code with the words in `denied` forbidden by the sandbox. Returns the error message, if any.
+fn run_denied(denied: &[&str], code: &str) -> Result<bool, String> {
+    let mut pack = Pack::new();
+    for word in denied {
+        pack.sandbox.deny(word);
+    }
+    let _ = pack.code(code);
+    pack.run().map_err(|e| e.msg)
+}
+
+/// Run the code with the words in `allowed` as the only permitted ones. Returns the stack, or the error message.
+fn run_allowed(allowed: &[&str], code: &str) -> Result<Vec<Cell>, String> {
+    let mut pack = Pack::new();
+    for word in allowed {
+        pack.sandbox.allow(word);
+    }
+    let _ = pack.code(code);
+    pack.run().map_err(|e| e.msg)?;
+    Ok(pack.stack.take())
+}
+
+#[test]
+fn allows_only_listed_words() {
+    assert_eq!(run_allowed(&["+"], "1 2 +"), Ok(vec![Cell::Integer(3)]));
+    assert_eq!(run_allowed(&["+"], "1 2 -").unwrap_err(), "Word '-' is not allowed by the sandbox");
+    assert_eq!(run_allowed(&["str."], "'ab' str.len"), Ok(vec![Cell::Integer(2)]));
+    assert_eq!(run_allowed(&["str."], "'ab' dup").unwrap_err(), "Word 'dup' is not allowed by the sandbox");
+}
+
+#[test]
+fn denials_take_precedence() {
+    let mut pack = Pack::new();
+    pack.sandbox.allow("str.");
+    pack.sandbox.deny("str.len");
+    let _ = pack.code("'ab' str.len");
+    assert_eq!(pack.run().unwrap_err().msg, "Word 'str.len' is not allowed by the sandbox");
+    assert_eq!(run_denied(&["str."], "'ab' str.len").unwrap_err(), "Word 'str.len' is not allowed by the sandbox");
+    // A lexicon pattern needs the dot, "str" is only the word with that name
+    assert!(run_denied(&["str"], "'ab' str.len").is_ok());
+}
+
+#[test]
+fn checks_indirect_execution() {
+    for code in ["@ dup exe", "1 { dup } exe", "1 true { dup } { } either", "#( 1 ) { dup } map", "{ dup } def d 1 d", "1 @ dup curry exe"] {
+        assert_eq!(run_denied(&["dup"], code).unwrap_err(), "Word 'dup' is not allowed by the sandbox", "code: {}", code);
+    }
+}
+
+#[test]
+fn checks_resolved_names() {
+    // The pattern is checked against the full name of the word
+    assert!(run_denied(&["dup"], "lex my { 1 } def dup \\lex my.dup").is_ok());
+    assert_eq!(run_denied(&["my.dup"], "lex my { 1 } def dup 5 dup").unwrap_err(), "Word 'my.dup' is not allowed by the sandbox");
+}
+
+#[test]
+fn introspection_checks_sandbox() {
+    let defs = "{ 1 } def secret ";
+    assert_eq!(run_denied(&["secret"], &(defs.to_owned() + "@ secret body exe")).unwrap_err(), "body: Word 'secret' is not allowed by the sandbox");
+    assert_eq!(run_denied(&["secret"], &(defs.to_owned() + "@ secret kind")).unwrap_err(), "kind: Word 'secret' is not allowed by the sandbox");
+    let mut pack = Pack::new();
+    pack.sandbox.deny("secret");
+    let _ = pack.code("{ 1 } def secret @ secret exist? @ dup exist?");
+    pack.run().unwrap();
+    assert_eq!(pack.stack.take(), vec![Cell::Word("secret".into()), false.into(), Cell::Word("dup".into()), true.into()]);
+}
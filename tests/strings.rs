@@ -0,0 +1,76 @@
+use runpack::{Pack, Cell};
+
+/// Run the code and return the stack, or the error message.
+fn run(code: &str) -> Result<Vec<Cell>, String> {
+    let mut pack = Pack::new();
+    let _ = pack.code(code);
+    pack.run().map_err(|e| e.msg)?;
+    Ok(pack.stack.take())
+}
+
+/// Run the code and get the single cell it leaves.
+fn eval(code: &str) -> Cell {
+    let mut stack = run(code).unwrap_or_else(|e| panic!("code: {}, error: {}", code, e));
+    assert_eq!(stack.len(), 1, "code: {}", code);
+    stack.pop().unwrap()
+}
+
+fn strings(values: &[&str]) -> Cell {
+    values.iter().map(|s| Cell::String((*s).into())).collect::<Vec<Cell>>().into()
+}
+
+#[test]
+fn counts_chars_and_bytes() {
+    assert_eq!(eval("'héllo' str.len"), Cell::Integer(5));
+    assert_eq!(eval("'héllo' str.bytes"), Cell::Integer(6));
+}
+
+#[test]
+fn indexes_by_char() {
+    assert_eq!(eval("'héllo' 1 3 str.slice"), "él".into());
+    assert_eq!(run("'héllo' 3 9 str.slice").unwrap_err(), "str.slice: Range 3..9 out of bounds");
+    assert_eq!(eval("'héllo' 'll' str.find"), Cell::Integer(2));
+    assert_eq!(eval("'héllo' 'x' str.find"), Cell::Integer(-1));
+    assert_eq!(eval("'héllo' 1 str.char_at"), "é".into());
+    assert_eq!(run("'héllo' 5 str.char_at").unwrap_err(), "str.char_at: Index 5 out of range");
+}
+
+#[test]
+fn splits_and_joins() {
+    assert_eq!(eval("'a,b' ',' str.split"), strings(&["a", "b"]));
+    assert_eq!(eval("',a,' ',' str.split"), strings(&["", "a", ""]));
+    // An empty separator splits into chars
+    assert_eq!(eval("'hé' '' str.split"), strings(&["h", "é"]));
+    assert_eq!(eval("#( 'a' 'b' ) ',' str.join"), "a,b".into());
+    assert_eq!(eval("( 'a' 'b' '-' str.join )"), "a-b".into());
+    assert_eq!(run("#( 'a' 1 ) ',' str.join").unwrap_err(), "str.join: All cells must be strings");
+    assert_eq!(eval("'a,b,c' ',' str.split '-' str.join"), "a-b-c".into());
+}
+
+#[test]
+fn transforms() {
+    assert_eq!(eval("'a-b-c' '-' '+' str.replace"), "a+b+c".into());
+    assert_eq!(run("'a' '' 'x' str.replace").unwrap_err(), "str.replace: The pattern is empty");
+    assert_eq!(eval("' hi ' str.trim"), "hi".into());
+    assert_eq!(eval("'héllo' str.upper"), "HÉLLO".into());
+    assert_eq!(eval("'ÀB' str.lower"), "àb".into());
+    assert_eq!(eval("'ab' 3 str.repeat"), "ababab".into());
+    assert_eq!(run("'ab' -1 str.repeat").unwrap_err(), "str.repeat: Invalid count -1");
+}
+
+#[test]
+fn checks_prefix_and_suffix() {
+    assert_eq!(eval("'héllo' 'hé' str.starts_with?"), true.into());
+    assert_eq!(eval("'héllo' 'lo' str.ends_with?"), true.into());
+    assert_eq!(eval("'héllo' 'lo' str.starts_with?"), false.into());
+}
+
+#[test]
+fn converts_char_codes() {
+    assert_eq!(eval("'é' str.code"), Cell::Integer(233));
+    assert_eq!(eval("233 str.from_code"), "é".into());
+    assert_eq!(run("'' str.code").unwrap_err(), "str.code: The string is empty");
+    // Surrogates aren't chars
+    assert_eq!(run("55296 str.from_code").unwrap_err(), "str.from_code: Invalid char code 55296");
+    assert_eq!(run("-1 str.from_code").unwrap_err(), "str.from_code: Invalid char code -1");
+}